@@ -2,28 +2,30 @@
 #version 330 core
 layout (location = 0) in vec2 aPos;
 layout (location = 1) in vec2 aTex;
+layout (location = 2) in vec4 aColor;
 
 out vec2 TexCoords;
+out vec4 SpriteColor;
 
-uniform mat4 model;
 uniform mat4 projection;
 
 void main()
 {
     TexCoords = aTex;
-    gl_Position = projection * model * vec4(aPos.x, aPos.y, 0.0, 1.0);
+    SpriteColor = aColor;
+    gl_Position = projection * vec4(aPos.x, aPos.y, 0.0, 1.0);
 }
 
 
 #shader fragment
 #version 330 core
 in vec2 TexCoords;
+in vec4 SpriteColor;
 out vec4 color;
 
 uniform sampler2D image;
-uniform vec3 spriteColor;
 
 void main()
 {    
-    color = vec4(spriteColor, 1.0) * texture(image, TexCoords);
+    color = SpriteColor * texture(image, TexCoords);
 }  
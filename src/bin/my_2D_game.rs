@@ -1,14 +1,29 @@
 use nalgebra_glm as glm;
 use omak::{
+    input::Key,
     panels::{
         common::{GamePanel, Runnable},
         glfw_panel::WindowGlfw,
     },
-    renderer::Renderer,
+    renderer::{assets::Handle, texture::Texture, utils::ResourcesManager, Renderer},
 };
+use std::collections::HashMap;
+
+const BOY_IMAGES: [&str; 8] = [
+    "resources/img/boy/boy_up_1.png",
+    "resources/img/boy/boy_up_2.png",
+    "resources/img/boy/boy_down_1.png",
+    "resources/img/boy/boy_down_2.png",
+    "resources/img/boy/boy_left_1.png",
+    "resources/img/boy/boy_left_2.png",
+    "resources/img/boy/boy_right_1.png",
+    "resources/img/boy/boy_right_2.png",
+];
 
 fn main() {
-    WindowGlfw::build(640, 400).run(MyGame::new());
+    let mut panel = WindowGlfw::build(640, 400);
+    let game = MyGame::new(&mut panel.get_renderer().res_manager);
+    panel.run(game);
 }
 
 //--------------------------------------------------------
@@ -18,16 +33,26 @@ pub struct MyGame {
 }
 
 impl Runnable for MyGame {
-    fn run(&mut self, panel: &mut impl GamePanel) {
+    fn update(&mut self, panel: &mut impl GamePanel, _dt: f32) {
         self.update(panel);
+    }
+
+    fn render(&mut self, panel: &mut impl GamePanel, _alpha: f32) {
         self.draw(panel);
     }
 }
 
 impl MyGame {
-    pub fn new() -> Self {
+    pub fn new(res_manager: &mut ResourcesManager) -> Self {
         Self {
-            player: Player::new(300, 200, 32, 32, "resources/img/boy/boy_down_1.png"),
+            player: Player::new(
+                300,
+                200,
+                32,
+                32,
+                "resources/img/boy/boy_down_1.png",
+                res_manager,
+            ),
         }
     }
 
@@ -51,9 +76,21 @@ pub struct Player {
     sprite_counter: i32,
     sprite_num: i32,
     image: String,
+    images: HashMap<String, Handle<Texture>>,
 }
 impl Player {
-    pub fn new(x: i32, y: i32, width: i32, height: i32, image: &str) -> Self {
+    pub fn new(
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        image: &str,
+        res_manager: &mut ResourcesManager,
+    ) -> Self {
+        let images = BOY_IMAGES
+            .iter()
+            .map(|path| (path.to_string(), res_manager.load_texture(path)))
+            .collect();
         Self {
             x,
             y,
@@ -63,6 +100,7 @@ impl Player {
             sprite_counter: 0,
             sprite_num: 0,
             image: image.to_string(),
+            images,
         }
     }
     fn update(&mut self, game_panel: &mut impl GamePanel) {
@@ -79,7 +117,7 @@ impl Player {
     }
 
     fn handle_keys_events(&mut self, game_panel: &mut impl GamePanel) {
-        if game_panel.get_keys()[glfw::Key::Up.get_scancode().unwrap() as usize] {
+        if game_panel.get_input().is_pressed(Key::Up) {
             if self.sprite_num == 1 {
                 self.image = "resources/img/boy/boy_up_1.png".to_string();
             } else {
@@ -87,7 +125,7 @@ impl Player {
             }
             self.y -= self.velocity;
         }
-        if game_panel.get_keys()[glfw::Key::Down.get_scancode().unwrap() as usize] {
+        if game_panel.get_input().is_pressed(Key::Down) {
             if self.sprite_num == 1 {
                 self.image = "resources/img/boy/boy_down_1.png".to_string();
             } else {
@@ -95,7 +133,7 @@ impl Player {
             }
             self.y += self.velocity;
         }
-        if game_panel.get_keys()[glfw::Key::Left.get_scancode().unwrap() as usize] {
+        if game_panel.get_input().is_pressed(Key::Left) {
             if self.sprite_num == 1 {
                 self.image = "resources/img/boy/boy_left_1.png".to_string();
             } else {
@@ -103,7 +141,7 @@ impl Player {
             }
             self.x -= self.velocity;
         }
-        if game_panel.get_keys()[glfw::Key::Right.get_scancode().unwrap() as usize] {
+        if game_panel.get_input().is_pressed(Key::Right) {
             if self.sprite_num == 1 {
                 self.image = "resources/img/boy/boy_right_1.png".to_string();
             } else {
@@ -120,7 +158,7 @@ impl Player {
             glm::vec2(self.width as f32, self.height as f32),
             0.0,
             glm::vec3(1.0, 1.0, 1.0),
-            &self.image,
+            &self.images[&self.image],
         );
     }
 }
@@ -1,4 +1,5 @@
-use omak::renderer::texture::Texture;
+use nalgebra_glm as glm;
+use omak::renderer::texture::SpriteSheet;
 use specs::{Component, VecStorage};
 
 #[derive(Component, Debug)]
@@ -16,6 +17,7 @@ pub struct EntityState {
     pub down: bool,
     pub jump: bool,
     pub in_air: bool,
+    pub facing_left: bool,
 }
 
 #[derive(Component, Debug)]
@@ -30,6 +32,8 @@ pub struct Position {
 pub struct Dimension {
     pub width: f32,
     pub height: f32,
+    /// Point of the sprite drawn at the center of the collider, from 0 to 1.
+    pub pivot: glm::Vec2,
 }
 
 #[derive(Component, Debug)]
@@ -42,7 +46,7 @@ pub struct Velocity {
 #[storage(VecStorage)]
 pub struct Animation {
     pub animations_kind: AnimationsKind,
-    pub animations: Vec<Texture>,
+    pub animations: SpriteSheet,
     pub animations_tick: i32,
     pub animations_index: usize,
     pub animations_speed: i32,
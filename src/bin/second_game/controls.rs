@@ -0,0 +1,106 @@
+use omak::input::{ActionMap, AxisBinding, Binding, GamepadAxis, GamepadButton, Key, MouseButton};
+
+/// Bindings the game ships with, never written to.
+const DEFAULT_CONTROLS: &str = "resources/controls.cfg";
+/// The player's rebindings, over the defaults, and kept out of the
+/// repository. Written on first run with the defaults commented out, so only
+/// the lines the player uncomments override them.
+const USER_CONTROLS: &str = "controls.user.cfg";
+
+pub fn load_controls() -> ActionMap {
+    let mut controls = ActionMap::load(DEFAULT_CONTROLS).unwrap_or_else(|e| {
+        log::warn!("Using the built-in controls: {}", e);
+        default_controls()
+    });
+    match ActionMap::load(USER_CONTROLS) {
+        Ok(overrides) => controls.merge(&overrides),
+        Err(omak::Error::Io { .. }) => {
+            if let Err(e) = std::fs::write(USER_CONTROLS, template(&controls)) {
+                log::warn!("Could not write {}: {}", USER_CONTROLS, e);
+            }
+        }
+        Err(e) => log::warn!("Ignoring the player's controls: {}", e),
+    }
+    controls
+}
+
+/// `controls` commented out, for the player to uncomment what they rebind.
+/// Bindings left alone keep following the game's defaults.
+fn template(controls: &ActionMap) -> String {
+    let mut template = String::from(
+        "# Your controls, over the game's. Uncomment a line and edit its bindings\n\
+         # to rebind that action; the others follow the game's defaults.\n",
+    );
+    for line in controls.to_string().lines() {
+        template.push_str("# ");
+        template.push_str(line);
+        template.push('\n');
+    }
+    template
+}
+
+fn default_controls() -> ActionMap {
+    ActionMap::new()
+        .with_axis(
+            "move",
+            &[
+                AxisBinding::Keys {
+                    negative: Key::Left,
+                    positive: Key::Right,
+                },
+                AxisBinding::Gamepad(GamepadAxis::LeftX),
+            ],
+        )
+        .with_action(
+            "jump",
+            &[
+                Binding::Key(Key::Space),
+                Binding::Gamepad(GamepadButton::South),
+            ],
+        )
+        .with_action(
+            "attack",
+            &[
+                Binding::Key(Key::Q),
+                Binding::Mouse(MouseButton::Left),
+                Binding::Gamepad(GamepadButton::West),
+            ],
+        )
+        .with_action(
+            "play",
+            &[Binding::Key(Key::P), Binding::Gamepad(GamepadButton::Start)],
+        )
+        .with_action(
+            "menu",
+            &[Binding::Key(Key::M), Binding::Gamepad(GamepadButton::Back)],
+        )
+        .with_action("hitboxes", &[Binding::Key(Key::H)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_template_only_overrides_what_is_uncommented() {
+        let template = template(&default_controls());
+        let untouched = ActionMap::parse(&template, USER_CONTROLS).unwrap();
+        assert_eq!(untouched.actions().count() + untouched.axes().count(), 0);
+
+        let rebound = template.replace("# action jump = key:Space", "action jump = key:Up");
+        let rebound = ActionMap::parse(&rebound, USER_CONTROLS).unwrap();
+        let mut controls = default_controls();
+        controls.merge(&rebound);
+        assert_eq!(
+            controls.bindings("jump"),
+            [
+                Binding::Key(Key::Up),
+                Binding::Gamepad(GamepadButton::South)
+            ]
+        );
+        assert_eq!(
+            controls.bindings("attack"),
+            default_controls().bindings("attack")
+        );
+    }
+}
@@ -1,8 +1,9 @@
-use crate::{GAME_HEIGHT, GAME_WIDTH, TILE_SIZE};
-use crate::{TILES_IN_HEIGHT, TILES_IN_WIDTH, TILE_SIZE_SCALED};
+use crate::TILE_SIZE_SCALED;
 use nalgebra_glm as glm;
-use omak::renderer::texture::{self, SpritesBuilder, Texture};
+use omak::renderer::texture::{self, SpriteSheet, SpritesBuilder};
+use omak::renderer::utils::ResourcesManager;
 use omak::renderer::{ImgKind, Renderer};
+use omak::{Error, Result};
 use std::path::Path;
 
 pub struct Level {
@@ -10,9 +11,12 @@ pub struct Level {
     lewel_max_width: usize,
 }
 impl Level {
-    pub fn new(level_image: &str) -> Self {
+    pub fn new(level_image: &str) -> Result<Self> {
         let mut level_data = Vec::new();
-        let image = image::open(&Path::new(level_image)).expect("Failed to load an image");
+        let image = image::open(Path::new(level_image)).map_err(|source| Error::Image {
+            path: level_image.to_string(),
+            source,
+        })?;
         for (x, y, pixel) in image.to_rgb8().enumerate_pixels_mut() {
             let image::Rgb(data) = *pixel;
             level_data.insert(
@@ -20,10 +24,19 @@ impl Level {
                 data[0],
             );
         }
-        Self {
+        Ok(Self {
             level_data,
             lewel_max_width: image.width() as usize,
-        }
+        })
+    }
+
+    /// Size in tiles.
+    pub fn width(&self) -> usize {
+        self.lewel_max_width
+    }
+
+    pub fn height(&self) -> usize {
+        self.level_data.len() / self.lewel_max_width
     }
 
     pub fn get_sprite_index(&self, x: usize, y: usize) -> usize {
@@ -32,23 +45,40 @@ impl Level {
 }
 
 pub struct LevelManager {
-    sprites: Vec<Texture>,
+    sprites: SpriteSheet,
     level: Level,
 }
 impl LevelManager {
-    pub fn new() -> Self {
-        Self {
-            sprites: SpritesBuilder::init("resources/img/outside_sprites.png", ImgKind::PNG)
+    pub fn new(res_manager: &mut ResourcesManager) -> Result<Self> {
+        let sprites = res_manager.try_load_sprite_sheet(
+            SpritesBuilder::init("resources/img/outside_sprites.png", ImgKind::PNG)
                 .with_rows(4, 32)
-                .with_columns(12, 32)
-                .build(),
-            level: Level::new("resources/img/level_one_data_long.png"),
-        }
+                .with_columns(12, 32),
+        )?;
+        Ok(Self {
+            // specs resources are shared between threads, handles are not
+            sprites: SpriteSheet::clone(&sprites),
+            level: Level::new("resources/img/level_one_data_long.png")?,
+        })
+    }
+
+    /// Size of the level in pixels.
+    pub fn size(&self) -> glm::Vec2 {
+        glm::vec2(
+            self.level.width() as f32 * TILE_SIZE_SCALED,
+            self.level.height() as f32 * TILE_SIZE_SCALED,
+        )
     }
 
+    /// Draws the tiles the camera can see.
     pub fn draw(&self, renderer: &mut Renderer) {
-        for y in 0..TILES_IN_HEIGHT as i32 {
-            for x in 0..(TILES_IN_WIDTH + 1.0) as i32 {
+        let (min, max) = renderer.camera.visible_area();
+        let first_column = (min.x / TILE_SIZE_SCALED).floor().max(0.0) as i32;
+        let last_column = ((max.x / TILE_SIZE_SCALED).ceil() as i32).min(self.level.width() as i32);
+        let first_row = (min.y / TILE_SIZE_SCALED).floor().max(0.0) as i32;
+        let last_row = ((max.y / TILE_SIZE_SCALED).ceil() as i32).min(self.level.height() as i32);
+        for y in first_row..last_row {
+            for x in first_column..last_column {
                 renderer.draw_image(
                     glm::vec2((x as f32 * TILE_SIZE_SCALED), y as f32 * TILE_SIZE_SCALED),
                     glm::vec2(TILE_SIZE_SCALED, TILE_SIZE_SCALED),
@@ -74,7 +104,8 @@ impl LevelManager {
     }
 
     fn is_solid(&self, x: f32, y: f32) -> bool {
-        if x < 0.0 || x >= GAME_WIDTH as f32 || y < 0.0 || y >= GAME_HEIGHT as f32 {
+        let size = self.size();
+        if x < 0.0 || x >= size.x || y < 0.0 || y >= size.y {
             return true;
         }
         let x_index = x / TILE_SIZE_SCALED;
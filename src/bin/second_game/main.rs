@@ -1,4 +1,5 @@
 mod components;
+mod controls;
 mod levels;
 mod menu;
 mod play;
@@ -6,15 +7,19 @@ mod systems;
 use components::*;
 use levels::LevelManager;
 use menu::Menu;
+use nalgebra_glm as glm;
+use omak::input::ActionMap;
 use omak::panels::{
     common::{GamePanel, Runnable},
+    window_config::WindowConfig,
     winit_panel::WindowWinit,
 };
-use omak::renderer::texture::SpritesBuilder;
+use omak::renderer::texture::{SpriteSheet, SpritesBuilder};
+use omak::renderer::utils::ResourcesManager;
+use omak::renderer::viewport::Scaling;
 use omak::renderer::ImgKind;
 use play::Play;
 use specs::{Builder, World, WorldExt};
-use winit::event::VirtualKeyCode;
 
 const TILE_SIZE: f32 = 32.0;
 const TILES_IN_WIDTH: f32 = 26.0;
@@ -27,51 +32,77 @@ const GAME_HEIGHT: u32 = (TILE_SIZE_SCALED * TILES_IN_HEIGHT) as u32;
 //--------------------------------------------------------
 
 fn main() {
-    WindowWinit::build(GAME_WIDTH, GAME_HEIGHT).run(MyGame::new());
+    let config = WindowConfig::init(GAME_WIDTH, GAME_HEIGHT)
+        .with_title("Second game")
+        .with_vsync(true)
+        .with_resizable(true)
+        .with_virtual_resolution(GAME_WIDTH, GAME_HEIGHT, Scaling::Fit);
+    let started = WindowWinit::try_build_with(config).and_then(|mut panel| {
+        let game = MyGame::new(&mut panel.get_renderer().res_manager)?;
+        Ok((panel, game))
+    });
+    match started {
+        Ok((panel, game)) => panel.run(game),
+        Err(e) => {
+            eprintln!("Omak failed to start: {}", e);
+            std::process::exit(1);
+        }
+    }
 }
 
 //--------------------------------------------------------
 
 pub struct MyGame {
+    controls: ActionMap,
     mode: GameMode,
     play: Play,
     menu: Menu,
 }
 
 impl Runnable for MyGame {
-    fn run(&mut self, panel: &mut impl GamePanel) {
+    fn update(&mut self, panel: &mut impl GamePanel, dt: f32) {
         match self.mode {
             GameMode::Menu => {
-                if panel.get_keys().contains(&VirtualKeyCode::P) {
+                if self.controls.just_pressed(panel.get_input(), "play") {
                     self.mode = GameMode::Playing;
-                } else {
-                    self.menu.run(panel);
                 }
             }
             GameMode::Playing => {
-                if panel.get_keys().contains(&VirtualKeyCode::M) {
+                if self.controls.just_pressed(panel.get_input(), "menu") {
                     self.mode = GameMode::Menu;
                 }
-                self.play.run(panel);
-            }
-            GameMode::End => {
-                unimplemented!();
+                self.play.update(panel, &self.controls, dt);
             }
         }
     }
+
+    fn render(&mut self, panel: &mut impl GamePanel, _alpha: f32) {
+        match self.mode {
+            GameMode::Menu => self.menu.render(panel),
+            GameMode::Playing => self.play.render(panel),
+        }
+    }
 }
 
 impl MyGame {
-    pub fn new() -> Self {
-        Self {
+    pub fn new(res_manager: &mut ResourcesManager) -> omak::Result<Self> {
+        Self::with_controls(res_manager, controls::load_controls())
+    }
+
+    pub fn with_controls(
+        res_manager: &mut ResourcesManager,
+        controls: ActionMap,
+    ) -> omak::Result<Self> {
+        Ok(Self {
+            controls,
             mode: GameMode::Playing,
-            play: Play::new(),
+            play: Play::new(res_manager)?,
             menu: Menu::new(),
-        }
+        })
     }
 }
 
-pub fn init_world() -> World {
+pub fn init_world(res_manager: &mut ResourcesManager) -> omak::Result<World> {
     let mut ecs = World::new();
     ecs.register::<Position>();
     ecs.register::<Player>();
@@ -81,8 +112,13 @@ pub fn init_world() -> World {
     ecs.register::<Animation>();
     ecs.register::<Colider>();
     ecs.register::<Jump>();
-    let level_manager = LevelManager::new();
+    let level_manager = LevelManager::new(res_manager)?;
     ecs.insert(level_manager);
+    let player_sprites = res_manager.try_load_sprite_sheet(
+        SpritesBuilder::init("resources/img/player_sprites.png", ImgKind::PNG)
+            .with_rows(9, 64)
+            .with_columns(6, 40),
+    )?;
 
     ecs.create_entity()
         .with(Player)
@@ -95,19 +131,19 @@ pub fn init_world() -> World {
             down: false,
             jump: false,
             in_air: false,
+            facing_left: false,
         })
         .with(Position { x: 320.0, y: 338.0 })
         .with(Dimension {
             width: 64.0 * SCALE,
             height: 40.0 * SCALE,
+            // the collider's center, in pixels of the 64x40 sprite
+            pivot: glm::vec2(32.0 / 64.0, 18.0 / 40.0),
         })
         .with(Velocity { velocity: 3.0 })
         .with(Animation {
             animations_kind: AnimationsKind::Idle,
-            animations: SpritesBuilder::init("resources/img/player_sprites.png", ImgKind::PNG)
-                .with_rows(9, 64)
-                .with_columns(6, 40)
-                .build(),
+            animations: SpriteSheet::clone(&player_sprites),
 
             animations_tick: 0,
             animations_index: 0,
@@ -126,11 +162,10 @@ pub fn init_world() -> World {
             fall_speed: 1.5 * SCALE,
         })
         .build();
-    ecs
+    Ok(ecs)
 }
 
 enum GameMode {
     Menu,
     Playing,
-    End,
 }
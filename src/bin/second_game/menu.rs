@@ -1,4 +1,6 @@
 use omak::panels::common::GamePanel;
+use omak::renderer::camera::Space;
+use omak::renderer::layer::Layer;
 
 pub struct Menu {
     pub touched: bool,
@@ -8,7 +10,13 @@ impl Menu {
         Self { touched: true }
     }
 
-    pub fn run(&mut self, panel: &mut impl GamePanel) {
-        panel.get_renderer().println(550.0, 150.0, 32.0, "MENU");
+    pub fn render(&mut self, panel: &mut impl GamePanel) {
+        let renderer = panel.get_renderer();
+        let (space, layer) = (renderer.space(), renderer.layer());
+        renderer.set_space(Space::Screen);
+        renderer.set_layer(Layer::Ui);
+        renderer.println(550.0, 150.0, 32.0, "MENU");
+        renderer.set_space(space);
+        renderer.set_layer(layer);
     }
 }
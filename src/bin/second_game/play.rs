@@ -1,58 +1,94 @@
 use crate::components::*;
 use crate::levels::LevelManager;
 use crate::systems::AnimationTick;
+use crate::{GAME_HEIGHT, GAME_WIDTH};
 use nalgebra_glm as glm;
+use omak::input::ActionMap;
 use omak::panels::common::GamePanel;
+use omak::renderer::color::Color;
+use omak::renderer::draw_params::DrawParams;
+use omak::renderer::layer::Layer;
 use omak::renderer::texture;
+use omak::renderer::utils::ResourcesManager;
 use specs::{Join, RunNow, World, WorldExt};
-use winit::event::VirtualKeyCode;
 
 pub struct Play {
     ecs: World,
+    show_hitboxes: bool,
 }
 impl Play {
-    pub fn new() -> Self {
-        Self {
-            ecs: crate::init_world(),
-        }
-    }
-    pub fn run(&mut self, panel: &mut impl GamePanel) {
-        self.update(panel);
-        self.draw(panel);
+    pub fn new(res_manager: &mut ResourcesManager) -> omak::Result<Self> {
+        Ok(Self {
+            ecs: crate::init_world(res_manager)?,
+            show_hitboxes: false,
+        })
     }
-
-    fn update(&mut self, game_panel: &mut impl GamePanel) {
+    /// Movement and animation speeds are per update, so `dt` is the same
+    /// every call.
+    pub fn update(&mut self, game_panel: &mut impl GamePanel, controls: &ActionMap, _dt: f32) {
         self.run_systems();
-        self.handle_keys_events(game_panel);
+        if controls.just_pressed(game_panel.get_input(), "hitboxes") {
+            self.show_hitboxes = !self.show_hitboxes;
+        }
+        self.handle_keys_events(game_panel, controls);
         self.update_position();
         self.set_animation();
     }
 
-    fn draw(&mut self, game_panel: &mut impl GamePanel) {
-        let x_offset = 33.0;
-        let y_offset = 8.0;
+    pub fn render(&mut self, game_panel: &mut impl GamePanel) {
         let level_manager = self.ecs.fetch::<LevelManager>();
-        level_manager.draw(&mut game_panel.get_renderer());
-
+        let players = self.ecs.read_storage::<Player>();
         let coliders = self.ecs.read_storage::<Colider>();
+
+        let camera = &mut game_panel.get_renderer().camera;
+        camera.set_deadzone(GAME_WIDTH as f32 * 0.4, GAME_HEIGHT as f32);
+        camera.set_bounds(Some((glm::vec2(0.0, 0.0), level_manager.size())));
+        for (_player, col) in (&players, &coliders).join() {
+            camera.follow(glm::vec2(col.x + col.width * 0.5, col.y + col.height * 0.5));
+        }
+
+        let renderer = game_panel.get_renderer();
+        let layer = renderer.layer();
+        renderer.set_layer(Layer::Tiles);
+        level_manager.draw(renderer);
+        renderer.set_layer(Layer::Entities);
+
         let mut animations = self.ecs.write_storage::<Animation>();
         let dimentions = self.ecs.read_storage::<Dimension>();
-        for (col, dimention, animation) in (&coliders, &dimentions, &mut animations).join() {
-            game_panel.get_renderer().draw_image(
-                glm::vec2(col.x - x_offset, col.y - y_offset),
-                glm::vec2(dimention.width, dimention.height),
-                0.0,
-                glm::vec3(1.0, 1.0, 1.0),
-                animation
-                    .animations
-                    .get(texture::get_index(
-                        animation.animations_index,
-                        animation.animations_kind.get_index_and_count().0,
-                        6,
-                    ))
-                    .unwrap(),
+        let states = self.ecs.read_storage::<EntityState>();
+        for (col, dimention, animation, st) in
+            (&coliders, &dimentions, &mut animations, &states).join()
+        {
+            let frame = animation
+                .animations
+                .get(texture::get_index(
+                    animation.animations_index,
+                    animation.animations_kind.get_index_and_count().0,
+                    6,
+                ))
+                .unwrap();
+            renderer.draw(
+                frame,
+                &DrawParams::at(glm::vec2(col.x + col.width * 0.5, col.y + col.height * 0.5))
+                    .with_size(glm::vec2(dimention.width, dimention.height))
+                    .with_pivot(dimention.pivot)
+                    .with_flip_x(st.facing_left)
+                    .with_depth(col.y + col.height),
             );
         }
+
+        if self.show_hitboxes {
+            renderer.set_layer(Layer::Foreground);
+            for col in coliders.join() {
+                renderer.stroke_rect(
+                    glm::vec2(col.x, col.y),
+                    glm::vec2(col.width, col.height),
+                    1.0,
+                    Color::RED,
+                );
+            }
+        }
+        renderer.set_layer(layer);
     }
 
     fn run_systems(&mut self) {
@@ -61,32 +97,20 @@ impl Play {
         self.ecs.maintain();
     }
 
-    fn handle_keys_events(&mut self, game_panel: &mut impl GamePanel) {
-        let keys = game_panel.get_keys();
+    fn handle_keys_events(&mut self, game_panel: &mut impl GamePanel, controls: &ActionMap) {
+        let input = game_panel.get_input();
         let players = self.ecs.read_storage::<Player>();
         let mut players_state = self.ecs.write_storage::<EntityState>();
 
+        let moving = controls.axis(input, "move");
         for (_player, st) in (&players, &mut players_state).join() {
-            if keys.contains(&VirtualKeyCode::Left) {
-                st.left = true;
-            } else {
-                st.left = false;
-            }
-            if keys.contains(&VirtualKeyCode::Right) {
-                st.right = true;
-            } else {
-                st.right = false;
-            }
-            if keys.contains(&VirtualKeyCode::Space) {
-                st.jump = true;
-            } else {
-                st.jump = false;
-            }
-            if keys.contains(&VirtualKeyCode::Q) {
-                st.attacking = true;
-            } else {
-                st.attacking = false;
+            st.left = moving <= -0.5;
+            st.right = moving >= 0.5;
+            if st.left != st.right {
+                st.facing_left = st.left;
             }
+            st.jump = controls.just_pressed(input, "jump");
+            st.attacking = controls.is_pressed(input, "attack");
         }
     }
 
@@ -212,13 +236,144 @@ impl Play {
     }
 }
 
-enum Actions {
-    MoveLeft,
-    MoveRight,
-    MoveUp,
-    MoveDown,
-    Idle,
-    Attacking,
-    Jump,
-    Fall,
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GameMode, MyGame};
+    use omak::input::Key;
+    use omak::panels::headless_panel::WindowHeadless;
+    use omak::panels::window_config::WindowConfig;
+    use omak::renderer::backends::software_backend::SoftwareBackend;
+    use specs::Component;
+
+    fn start(panel: WindowHeadless) -> (WindowHeadless, MyGame) {
+        let mut panel = panel;
+        let controls = ActionMap::load("resources/controls.cfg").unwrap();
+        let game = MyGame::with_controls(&mut panel.get_renderer().res_manager, controls).unwrap();
+        (panel, game)
+    }
+
+    fn panel() -> WindowHeadless {
+        WindowHeadless::try_build_with(WindowConfig::init(GAME_WIDTH, GAME_HEIGHT)).unwrap()
+    }
+
+    fn player<T: Component, R>(game: &MyGame, read: impl Fn(&T) -> R) -> R {
+        let players = game.play.ecs.read_storage::<Player>();
+        let storage = game.play.ecs.read_storage::<T>();
+        let (_, component) = (&players, &storage).join().next().unwrap();
+        read(component)
+    }
+
+    #[test]
+    fn space_makes_the_player_jump_then_fall_back() {
+        let (mut panel, mut game) = start(panel().with_keys(2, &[Key::Space]));
+        panel.run_frames(&mut game, 2);
+        let floor = player(&game, |col: &Colider| col.y);
+        assert!(!player(&game, |st: &EntityState| st.in_air));
+
+        panel.step(&mut game);
+        assert!(player(&game, |st: &EntityState| st.in_air));
+        assert!(player(&game, |jmp: &Jump| jmp.air_speed) < 0.0);
+        assert_eq!(
+            player(&game, |ani: &Animation| ani.animations_kind),
+            AnimationsKind::Jumping
+        );
+
+        panel.run_frames(&mut game, 20);
+        assert!(player(&game, |col: &Colider| col.y) < floor);
+
+        // up and down again, at most a few seconds of updates
+        let mut landed = false;
+        for _ in 0..600 {
+            panel.step(&mut game);
+            if !player(&game, |st: &EntityState| st.in_air) {
+                landed = true;
+                break;
+            }
+        }
+        assert!(landed);
+        // it stops short of the floor by less than a step of its fall
+        let step = player(&game, |jmp: &Jump| jmp.jump_speed.abs());
+        assert!((player(&game, |col: &Colider| col.y) - floor).abs() < step);
+        // the landing update still counts as a move
+        panel.step(&mut game);
+        assert_eq!(
+            player(&game, |ani: &Animation| ani.animations_kind),
+            AnimationsKind::Idle
+        );
+    }
+
+    #[test]
+    fn holding_right_runs_right() {
+        let (mut panel, mut game) = start(panel().hold_keys(1..31, &[Key::Right]));
+        panel.step(&mut game);
+        let start = player(&game, |col: &Colider| col.x);
+
+        panel.run_frames(&mut game, 30);
+        assert!(player(&game, |col: &Colider| col.x) > start);
+        assert!(!player(&game, |st: &EntityState| st.facing_left));
+        assert_eq!(
+            player(&game, |ani: &Animation| ani.animations_kind),
+            AnimationsKind::Running
+        );
+    }
+
+    #[test]
+    fn menu_pauses_the_game() {
+        let (mut panel, mut game) = start(
+            panel()
+                .with_keys(1, &[Key::M])
+                .hold_keys(2..10, &[Key::Right])
+                .with_keys(10, &[Key::P]),
+        );
+        panel.step(&mut game);
+        let start = player(&game, |col: &Colider| col.x);
+
+        panel.run_frames(&mut game, 9);
+        assert!(matches!(game.mode, GameMode::Menu));
+        assert_eq!(player(&game, |col: &Colider| col.x), start);
+
+        panel.step(&mut game);
+        assert!(matches!(game.mode, GameMode::Playing));
+    }
+
+    const GOLDEN: &str = "resources/golden/level_one.png";
+
+    /// Renders the start of the level in software and compares it with
+    /// `resources/golden/level_one.png`. After an intended visual change,
+    /// regenerate it with `OMAK_UPDATE_GOLDEN=1 cargo test --bin second_game golden`
+    /// and check the new image in. On a mismatch the frame that was drawn is
+    /// written to `target/golden/level_one.png`.
+    #[test]
+    fn the_level_start_matches_its_golden_image() {
+        let panel = panel().with_backend(Box::new(SoftwareBackend::new(GAME_WIDTH, GAME_HEIGHT)));
+        let (mut panel, mut game) = start(panel);
+        panel.step(&mut game);
+        let frame = panel.get_renderer().snapshot().unwrap();
+
+        if std::env::var_os("OMAK_UPDATE_GOLDEN").is_some() {
+            std::fs::create_dir_all("resources/golden").unwrap();
+            frame.save(GOLDEN).unwrap();
+            return;
+        }
+        let golden = image::open(GOLDEN)
+            .unwrap_or_else(|e| panic!("{}: {}, set OMAK_UPDATE_GOLDEN=1 to create it", GOLDEN, e))
+            .to_rgba8();
+        let differing = golden
+            .pixels()
+            .zip(frame.pixels())
+            .filter(|(a, b)| a != b)
+            .count();
+        if golden.dimensions() != frame.dimensions() || differing != 0 {
+            std::fs::create_dir_all("target/golden").unwrap();
+            frame.save("target/golden/level_one.png").unwrap();
+            panic!(
+                "{:?} frame, {} pixels differ from the {:?} {}, see target/golden/level_one.png",
+                frame.dimensions(),
+                differing,
+                golden.dimensions(),
+                GOLDEN
+            );
+        }
+    }
 }
@@ -0,0 +1,64 @@
+use std::fmt;
+use std::io;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Failures while creating a panel or loading assets.
+#[derive(Debug)]
+pub enum Error {
+    /// A file could not be read or written.
+    Io { path: String, source: io::Error },
+    /// An image could not be opened or decoded.
+    Image {
+        path: String,
+        source: image::ImageError,
+    },
+    /// A shader stage did not compile, or the program did not link.
+    Shader {
+        path: String,
+        stage: String,
+        log: String,
+    },
+    /// The window or its GL context could not be created.
+    Context(String),
+    /// A line of a config file, e.g. an `ActionMap`, could not be parsed.
+    Config {
+        path: String,
+        line: usize,
+        message: String,
+    },
+    /// A font file is not in a format omak reads.
+    Font { path: String, message: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "failed to access {}: {}", path, source),
+            Error::Image { path, source } => write!(f, "failed to load image {}: {}", path, source),
+            Error::Shader { path, stage, log } => {
+                write!(f, "{} shader {} failed to build:\n{}", stage, path, log)
+            }
+            Error::Context(reason) => write!(f, "failed to create a window: {}", reason),
+            Error::Config {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path, line, message),
+            Error::Font { path, message } => write!(f, "failed to load font {}: {}", path, message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Image { source, .. } => Some(source),
+            Error::Shader { .. }
+            | Error::Context(_)
+            | Error::Config { .. }
+            | Error::Font { .. } => None,
+        }
+    }
+}
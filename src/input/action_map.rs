@@ -0,0 +1,459 @@
+use crate::error::{Error, Result};
+use crate::input::{GamepadAxis, GamepadButton, InputState, Key, MouseButton};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+
+/// An input an action can be bound to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(Key),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+}
+
+impl Binding {
+    fn is_pressed(self, input: &InputState) -> bool {
+        match self {
+            Binding::Key(key) => input.is_pressed(key),
+            Binding::Mouse(button) => input.is_mouse_pressed(button),
+            Binding::Gamepad(button) => input.is_gamepad_pressed(button),
+        }
+    }
+
+    fn just_pressed(self, input: &InputState) -> bool {
+        match self {
+            Binding::Key(key) => input.just_pressed(key),
+            Binding::Mouse(button) => input.mouse_just_pressed(button),
+            Binding::Gamepad(button) => input.gamepad_just_pressed(button),
+        }
+    }
+
+    fn just_released(self, input: &InputState) -> bool {
+        match self {
+            Binding::Key(key) => input.just_released(key),
+            Binding::Mouse(button) => input.mouse_just_released(button),
+            Binding::Gamepad(button) => input.gamepad_just_released(button),
+        }
+    }
+
+    fn parse(binding: &str) -> std::result::Result<Self, String> {
+        let (kind, name) = split_binding(binding)?;
+        let parsed = match kind {
+            "key" => Key::from_name(name).map(Binding::Key),
+            "mouse" => MouseButton::from_name(name).map(Binding::Mouse),
+            "gamepad" => GamepadButton::from_name(name).map(Binding::Gamepad),
+            _ => return Err(format!("unknown binding kind `{}`", kind)),
+        };
+        parsed.ok_or_else(|| format!("unknown {} `{}`", kind, name))
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "key:{}", key.name()),
+            Binding::Mouse(button) => write!(f, "mouse:{}", button.name()),
+            Binding::Gamepad(button) => write!(f, "gamepad:{}", button.name()),
+        }
+    }
+}
+
+/// An input driving an axis, from -1 to 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AxisBinding {
+    /// -1 while `negative` is held, 1 while `positive` is, 0 for both or none.
+    Keys {
+        negative: Key,
+        positive: Key,
+    },
+    Gamepad(GamepadAxis),
+}
+
+impl AxisBinding {
+    fn value(self, input: &InputState) -> f32 {
+        match self {
+            AxisBinding::Keys { negative, positive } => {
+                input.is_pressed(positive) as i32 as f32 - input.is_pressed(negative) as i32 as f32
+            }
+            AxisBinding::Gamepad(axis) => input.gamepad_axis(axis),
+        }
+    }
+
+    fn parse(binding: &str) -> std::result::Result<Self, String> {
+        let (kind, name) = split_binding(binding)?;
+        match kind {
+            "keys" => {
+                let (negative, positive) = name.split_once('/').ok_or_else(|| {
+                    format!("expected `keys:<negative>/<positive>`, got `{}`", binding)
+                })?;
+                let key = |name: &str| {
+                    Key::from_name(name.trim()).ok_or_else(|| format!("unknown key `{}`", name))
+                };
+                Ok(AxisBinding::Keys {
+                    negative: key(negative)?,
+                    positive: key(positive)?,
+                })
+            }
+            "gamepad_axis" => GamepadAxis::from_name(name)
+                .map(AxisBinding::Gamepad)
+                .ok_or_else(|| format!("unknown gamepad_axis `{}`", name)),
+            _ => Err(format!("unknown axis binding kind `{}`", kind)),
+        }
+    }
+}
+
+impl fmt::Display for AxisBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AxisBinding::Keys { negative, positive } => {
+                write!(f, "keys:{}/{}", negative.name(), positive.name())
+            }
+            AxisBinding::Gamepad(axis) => write!(f, "gamepad_axis:{}", axis.name()),
+        }
+    }
+}
+
+/// Named actions and axes bound to keys, mouse buttons and gamepad inputs,
+/// so games ask for "jump" instead of a key and players can rebind it.
+///
+/// Saved as text, one action or axis per line:
+///
+/// ```text
+/// # comments start with #
+/// action jump = key:Space, gamepad:South
+/// action attack = key:Q, mouse:Left
+/// axis move = keys:Left/Right, gamepad_axis:LeftX
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ActionMap {
+    actions: BTreeMap<String, Vec<Binding>>,
+    axes: BTreeMap<String, Vec<AxisBinding>>,
+}
+
+impl ActionMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `bindings` to `action`.
+    pub fn with_action(mut self, action: &str, bindings: &[Binding]) -> Self {
+        for binding in bindings {
+            self.bind(action, *binding);
+        }
+        self
+    }
+
+    /// Adds `bindings` to `axis`.
+    pub fn with_axis(mut self, axis: &str, bindings: &[AxisBinding]) -> Self {
+        for binding in bindings {
+            self.bind_axis(axis, *binding);
+        }
+        self
+    }
+
+    /// Also triggers `action` from `binding`.
+    pub fn bind(&mut self, action: &str, binding: Binding) {
+        let bindings = self.actions.entry(action.to_string()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind(&mut self, action: &str, binding: Binding) {
+        if let Some(bindings) = self.actions.get_mut(action) {
+            bindings.retain(|bound| *bound != binding);
+        }
+    }
+
+    pub fn bind_axis(&mut self, axis: &str, binding: AxisBinding) {
+        let bindings = self.axes.entry(axis.to_string()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind_axis(&mut self, axis: &str, binding: AxisBinding) {
+        if let Some(bindings) = self.axes.get_mut(axis) {
+            bindings.retain(|bound| *bound != binding);
+        }
+    }
+
+    /// Removes every binding of the action and of the axis called `name`,
+    /// e.g. before rebinding it.
+    pub fn clear(&mut self, name: &str) {
+        if let Some(bindings) = self.actions.get_mut(name) {
+            bindings.clear();
+        }
+        if let Some(bindings) = self.axes.get_mut(name) {
+            bindings.clear();
+        }
+    }
+
+    /// Takes the bindings of every action and axis of `overrides` in place
+    /// of its own, e.g. a player's rebindings over the game's defaults.
+    pub fn merge(&mut self, overrides: &ActionMap) {
+        for (action, bindings) in &overrides.actions {
+            self.actions.insert(action.clone(), bindings.clone());
+        }
+        for (axis, bindings) in &overrides.axes {
+            self.axes.insert(axis.clone(), bindings.clone());
+        }
+    }
+
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn axis_bindings(&self, axis: &str) -> &[AxisBinding] {
+        self.axes.get(axis).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn actions(&self) -> impl Iterator<Item = &str> {
+        self.actions.keys().map(String::as_str)
+    }
+
+    pub fn axes(&self) -> impl Iterator<Item = &str> {
+        self.axes.keys().map(String::as_str)
+    }
+
+    /// True while any binding of `action` is held.
+    pub fn is_pressed(&self, input: &InputState, action: &str) -> bool {
+        self.bindings(action).iter().any(|b| b.is_pressed(input))
+    }
+
+    /// True during the update where `action` starts being held, whichever
+    /// binding did it.
+    pub fn just_pressed(&self, input: &InputState, action: &str) -> bool {
+        let bindings = self.bindings(action);
+        bindings.iter().any(|b| b.just_pressed(input))
+            && bindings
+                .iter()
+                .all(|b| !b.is_pressed(input) || b.just_pressed(input))
+    }
+
+    /// True during the update where the last held binding of `action` is released.
+    pub fn just_released(&self, input: &InputState, action: &str) -> bool {
+        let bindings = self.bindings(action);
+        bindings.iter().any(|b| b.just_released(input))
+            && !bindings.iter().any(|b| b.is_pressed(input))
+    }
+
+    /// Sum of the bindings of `axis`, clamped to [-1, 1].
+    pub fn axis(&self, input: &InputState, axis: &str) -> f32 {
+        self.axis_bindings(axis)
+            .iter()
+            .map(|b| b.value(input))
+            .sum::<f32>()
+            .clamp(-1.0, 1.0)
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        let source = fs::read_to_string(path).map_err(|source| Error::Io {
+            path: path.to_string(),
+            source,
+        })?;
+        Self::parse(&source, path)
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        fs::write(path, self.to_string()).map_err(|source| Error::Io {
+            path: path.to_string(),
+            source,
+        })
+    }
+
+    /// Reads the text format; `path` only names the source in errors.
+    pub fn parse(source: &str, path: &str) -> Result<Self> {
+        let mut map = Self::new();
+        for (i, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            map.parse_line(line).map_err(|message| Error::Config {
+                path: path.to_string(),
+                line: i + 1,
+                message,
+            })?;
+        }
+        Ok(map)
+    }
+
+    fn parse_line(&mut self, line: &str) -> std::result::Result<(), String> {
+        let (kind, rest) = line.split_once(char::is_whitespace).ok_or_else(|| {
+            format!(
+                "expected `action <name> = ...` or `axis <name> = ...`, got `{}`",
+                line
+            )
+        })?;
+        let (name, bindings) = rest
+            .split_once('=')
+            .ok_or_else(|| format!("missing `=` after `{}`", rest.trim()))?;
+        let name = name.trim();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(format!("`{}` is not a valid name", name));
+        }
+        let bindings = bindings.split(',').map(str::trim).filter(|b| !b.is_empty());
+        match kind {
+            "action" => {
+                self.actions.entry(name.to_string()).or_default();
+                for binding in bindings {
+                    self.bind(name, Binding::parse(binding)?);
+                }
+            }
+            "axis" => {
+                self.axes.entry(name.to_string()).or_default();
+                for binding in bindings {
+                    self.bind_axis(name, AxisBinding::parse(binding)?);
+                }
+            }
+            _ => return Err(format!("expected `action` or `axis`, got `{}`", kind)),
+        }
+        Ok(())
+    }
+}
+
+/// The text format read by `ActionMap::parse`.
+impl fmt::Display for ActionMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (action, bindings) in &self.actions {
+            write!(f, "action {} =", action)?;
+            write_list(f, bindings)?;
+        }
+        for (axis, bindings) in &self.axes {
+            write!(f, "axis {} =", axis)?;
+            write_list(f, bindings)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_list(f: &mut fmt::Formatter<'_>, bindings: &[impl fmt::Display]) -> fmt::Result {
+    for (i, binding) in bindings.iter().enumerate() {
+        let separator = if i == 0 { " " } else { ", " };
+        write!(f, "{}{}", separator, binding)?;
+    }
+    writeln!(f)
+}
+
+/// Splits `kind:name`.
+fn split_binding(binding: &str) -> std::result::Result<(&str, &str), String> {
+    binding
+        .split_once(':')
+        .map(|(kind, name)| (kind.trim(), name.trim()))
+        .ok_or_else(|| format!("expected `<kind>:<name>`, got `{}`", binding))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "\
+# comment
+action jump = key:Space, gamepad:South
+
+action attack = key:Q, mouse:Left
+action empty =
+axis move = keys:Left/Right, gamepad_axis:LeftX
+";
+
+    fn config_error(source: &str) -> (usize, String) {
+        match ActionMap::parse(source, "test.cfg") {
+            Err(Error::Config {
+                path,
+                line,
+                message,
+            }) => {
+                assert_eq!(path, "test.cfg");
+                (line, message)
+            }
+            other => panic!("expected a config error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_actions_and_axes() {
+        let map = ActionMap::parse(CONFIG, "test.cfg").unwrap();
+        assert_eq!(
+            map.bindings("jump"),
+            [
+                Binding::Key(Key::Space),
+                Binding::Gamepad(GamepadButton::South)
+            ]
+        );
+        assert_eq!(
+            map.bindings("attack"),
+            [Binding::Key(Key::Q), Binding::Mouse(MouseButton::Left)]
+        );
+        assert_eq!(
+            map.axis_bindings("move"),
+            [
+                AxisBinding::Keys {
+                    negative: Key::Left,
+                    positive: Key::Right
+                },
+                AxisBinding::Gamepad(GamepadAxis::LeftX)
+            ]
+        );
+        assert!(map.bindings("missing").is_empty());
+    }
+
+    #[test]
+    fn saved_text_parses_back_to_the_same_map() {
+        let map = ActionMap::parse(CONFIG, "test.cfg").unwrap();
+        let saved = map.to_string();
+        let reparsed = ActionMap::parse(&saved, "saved.cfg").unwrap();
+        assert_eq!(reparsed.to_string(), saved);
+        assert_eq!(
+            reparsed.actions().collect::<Vec<_>>(),
+            ["attack", "empty", "jump"]
+        );
+        assert_eq!(reparsed.axes().collect::<Vec<_>>(), ["move"]);
+        assert_eq!(reparsed.bindings("jump"), map.bindings("jump"));
+        assert_eq!(reparsed.axis_bindings("move"), map.axis_bindings("move"));
+    }
+
+    #[test]
+    fn errors_point_at_their_line() {
+        let (line, message) = config_error("# ok\naction jump = key:Space\naction jump\n");
+        assert_eq!(line, 3);
+        assert_eq!(message, "missing `=` after `jump`");
+
+        let (line, message) = config_error("\n\naction jump = key:Nope");
+        assert_eq!(line, 3);
+        assert_eq!(message, "unknown key `Nope`");
+
+        let (line, message) = config_error("axis move = keys:Left");
+        assert_eq!(line, 1);
+        assert_eq!(
+            message,
+            "expected `keys:<negative>/<positive>`, got `keys:Left`"
+        );
+
+        let (line, message) = config_error("button jump = key:Space");
+        assert_eq!(line, 1);
+        assert_eq!(message, "expected `action` or `axis`, got `button`");
+    }
+
+    #[test]
+    fn clear_empties_the_action_and_the_axis() {
+        let mut map = ActionMap::parse(CONFIG, "test.cfg")
+            .unwrap()
+            .with_action("move", &[Binding::Key(Key::D)]);
+        map.clear("move");
+        assert!(map.bindings("move").is_empty());
+        assert!(map.axis_bindings("move").is_empty());
+        assert_eq!(map.bindings("jump").len(), 2);
+    }
+
+    #[test]
+    fn merge_replaces_only_the_overridden_bindings() {
+        let mut map = ActionMap::parse(CONFIG, "test.cfg").unwrap();
+        let overrides = ActionMap::parse("action jump = key:W", "user.cfg").unwrap();
+        map.merge(&overrides);
+        assert_eq!(map.bindings("jump"), [Binding::Key(Key::W)]);
+        assert_eq!(map.bindings("attack").len(), 2);
+        assert_eq!(map.axis_bindings("move").len(), 2);
+    }
+}
@@ -0,0 +1,306 @@
+use nalgebra_glm as glm;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::input::keys::named_enum;
+use crate::input::Buttons;
+
+named_enum! {
+    /// Gamepad buttons, in the standard (Xbox-like) layout. Face buttons are
+    /// named after their position.
+    pub enum GamepadButton {
+        South,
+        East,
+        West,
+        North,
+        LeftBumper,
+        RightBumper,
+        Back,
+        Start,
+        Guide,
+        LeftThumb,
+        RightThumb,
+        DPadUp,
+        DPadRight,
+        DPadDown,
+        DPadLeft,
+    }
+}
+
+named_enum! {
+    /// Gamepad axes. Sticks go from -1 to 1, right and down positive; triggers
+    /// from 0 to 1.
+    pub enum GamepadAxis {
+        LeftX,
+        LeftY,
+        RightX,
+        RightY,
+        LeftTrigger,
+        RightTrigger,
+    }
+}
+
+/// Identifies a gamepad while it stays connected. Sources may give the id of
+/// a disconnected pad to the next one plugged in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GamepadId(pub u32);
+
+/// A gamepad plugged in or out, seen by the update following it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GamepadEvent {
+    Connected(GamepadId),
+    Disconnected(GamepadId),
+}
+
+/// Raw state of a gamepad, as its source reports it: axes before deadzones.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GamepadReading {
+    pub name: String,
+    pub buttons: HashSet<GamepadButton>,
+    pub axes: HashMap<GamepadAxis, f32>,
+}
+
+/// Where a panel finds gamepads. It is polled once per frame, before the
+/// updates; pads missing from a poll are disconnected.
+pub trait GamepadSource {
+    fn poll(&mut self) -> Vec<(GamepadId, GamepadReading)>;
+}
+
+/// A `GamepadSource` driven by code instead of hardware. Clones share their
+/// pads, so a test keeps one to press buttons on and hands another to the panel.
+#[derive(Clone, Debug, Default)]
+pub struct SimulatedGamepads {
+    pads: Rc<RefCell<BTreeMap<GamepadId, GamepadReading>>>,
+}
+
+impl SimulatedGamepads {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Plugs in a pad with nothing pressed. Does nothing if `id` is connected.
+    pub fn connect(&self, id: GamepadId, name: &str) {
+        self.pads
+            .borrow_mut()
+            .entry(id)
+            .or_insert_with(|| GamepadReading {
+                name: name.to_string(),
+                ..GamepadReading::default()
+            });
+    }
+
+    pub fn disconnect(&self, id: GamepadId) {
+        self.pads.borrow_mut().remove(&id);
+    }
+
+    /// Presses `button` on pad `id`, if connected.
+    pub fn press(&self, id: GamepadId, button: GamepadButton) {
+        if let Some(pad) = self.pads.borrow_mut().get_mut(&id) {
+            pad.buttons.insert(button);
+        }
+    }
+
+    pub fn release(&self, id: GamepadId, button: GamepadButton) {
+        if let Some(pad) = self.pads.borrow_mut().get_mut(&id) {
+            pad.buttons.remove(&button);
+        }
+    }
+
+    /// Moves `axis` of pad `id` to the raw `value`, if connected.
+    pub fn set_axis(&self, id: GamepadId, axis: GamepadAxis, value: f32) {
+        if let Some(pad) = self.pads.borrow_mut().get_mut(&id) {
+            pad.axes.insert(axis, value);
+        }
+    }
+}
+
+impl GamepadSource for SimulatedGamepads {
+    fn poll(&mut self) -> Vec<(GamepadId, GamepadReading)> {
+        self.pads
+            .borrow()
+            .iter()
+            .map(|(id, pad)| (*id, pad.clone()))
+            .collect()
+    }
+}
+
+/// A connected gamepad as seen by the game: button transitions, and axes
+/// with the deadzone applied.
+#[derive(Clone, Debug)]
+pub struct Gamepad {
+    name: String,
+    buttons: Buttons<GamepadButton>,
+    axes: HashMap<GamepadAxis, f32>,
+}
+
+impl Gamepad {
+    pub(crate) fn new(name: String) -> Self {
+        Self {
+            name,
+            buttons: Buttons::default(),
+            axes: HashMap::new(),
+        }
+    }
+
+    /// The name reported by the driver, e.g. to show in a controls menu.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_pressed(&self, button: GamepadButton) -> bool {
+        self.buttons.is_held(button)
+    }
+
+    pub fn just_pressed(&self, button: GamepadButton) -> bool {
+        self.buttons.pressed.contains(&button)
+    }
+
+    pub fn just_released(&self, button: GamepadButton) -> bool {
+        self.buttons.released.contains(&button)
+    }
+
+    pub fn held_for(&self, button: GamepadButton) -> Option<Duration> {
+        self.buttons.held.get(&button).copied()
+    }
+
+    /// Position of `axis` past the deadzone, rescaled so it still reaches 1.
+    pub fn axis(&self, axis: GamepadAxis) -> f32 {
+        self.axes.get(&axis).copied().unwrap_or(0.0)
+    }
+
+    /// Takes in a new reading, turning button changes into transitions.
+    pub(crate) fn apply(&mut self, reading: &GamepadReading, deadzone: f32) {
+        let released: Vec<GamepadButton> = self
+            .buttons
+            .held
+            .keys()
+            .copied()
+            .filter(|button| !reading.buttons.contains(button))
+            .collect();
+        for button in released {
+            self.buttons.release(button);
+        }
+        for button in &reading.buttons {
+            self.buttons.press(*button);
+        }
+
+        let raw = |axis| reading.axes.get(&axis).copied().unwrap_or(0.0);
+        self.axes.clear();
+        for (x, y) in [
+            (GamepadAxis::LeftX, GamepadAxis::LeftY),
+            (GamepadAxis::RightX, GamepadAxis::RightY),
+        ] {
+            let stick = radial_deadzone(glm::vec2(raw(x), raw(y)), deadzone);
+            self.axes.insert(x, stick.x);
+            self.axes.insert(y, stick.y);
+        }
+        for trigger in [GamepadAxis::LeftTrigger, GamepadAxis::RightTrigger] {
+            let value = raw(trigger).clamp(0.0, 1.0);
+            self.axes.insert(trigger, rescale(value, deadzone));
+        }
+    }
+
+    pub(crate) fn end_update(&mut self, dt: Duration) {
+        self.buttons.end_update(dt);
+    }
+}
+
+/// Deadzone over the stick's distance from the center rather than per axis,
+/// so diagonals are not snapped to the axes.
+fn radial_deadzone(stick: glm::Vec2, deadzone: f32) -> glm::Vec2 {
+    let length = glm::length(&stick);
+    if length <= deadzone {
+        return glm::Vec2::zeros();
+    }
+    stick * (rescale(length.min(1.0), deadzone) / length)
+}
+
+/// Maps [deadzone, 1] to [0, 1]. A deadzone of 1 or more swallows everything.
+fn rescale(value: f32, deadzone: f32) -> f32 {
+    if value <= deadzone || deadzone >= 1.0 {
+        0.0
+    } else {
+        (value - deadzone) / (1.0 - deadzone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(axes: &[(GamepadAxis, f32)]) -> GamepadReading {
+        GamepadReading {
+            axes: axes.iter().copied().collect(),
+            ..GamepadReading::default()
+        }
+    }
+
+    #[test]
+    fn simulated_pads_are_shared_between_clones() {
+        let pads = SimulatedGamepads::new();
+        let mut source = pads.clone();
+        pads.connect(GamepadId(1), "Pad");
+        pads.press(GamepadId(1), GamepadButton::South);
+        pads.set_axis(GamepadId(1), GamepadAxis::LeftX, 0.5);
+        // not connected: ignored
+        pads.press(GamepadId(2), GamepadButton::South);
+
+        let polled = source.poll();
+        assert_eq!(polled.len(), 1);
+        let (id, reading) = &polled[0];
+        assert_eq!(*id, GamepadId(1));
+        assert_eq!(reading.name, "Pad");
+        assert!(reading.buttons.contains(&GamepadButton::South));
+        assert_eq!(reading.axes[&GamepadAxis::LeftX], 0.5);
+
+        pads.disconnect(GamepadId(1));
+        assert!(source.poll().is_empty());
+    }
+
+    #[test]
+    fn radial_deadzone_keeps_diagonals() {
+        let mut pad = Gamepad::new("Pad".to_string());
+        // each axis is inside the deadzone, the stick is not
+        let diagonal = 0.25 * std::f32::consts::SQRT_2;
+        pad.apply(
+            &reading(&[
+                (GamepadAxis::LeftX, diagonal),
+                (GamepadAxis::LeftY, diagonal),
+            ]),
+            0.3,
+        );
+        assert!(pad.axis(GamepadAxis::LeftX) > 0.0);
+        assert!((pad.axis(GamepadAxis::LeftX) - pad.axis(GamepadAxis::LeftY)).abs() < 1e-6);
+
+        pad.apply(&reading(&[(GamepadAxis::LeftX, 0.2)]), 0.3);
+        assert_eq!(pad.axis(GamepadAxis::LeftX), 0.0);
+    }
+
+    #[test]
+    fn axes_still_reach_one_past_the_deadzone() {
+        let mut pad = Gamepad::new("Pad".to_string());
+        pad.apply(
+            &reading(&[(GamepadAxis::RightY, -1.0), (GamepadAxis::LeftTrigger, 1.0)]),
+            0.25,
+        );
+        assert!((pad.axis(GamepadAxis::RightY) + 1.0).abs() < 1e-6);
+        assert!((pad.axis(GamepadAxis::LeftTrigger) - 1.0).abs() < 1e-6);
+
+        pad.apply(&reading(&[(GamepadAxis::LeftTrigger, 0.625)]), 0.25);
+        assert!((pad.axis(GamepadAxis::LeftTrigger) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn full_deadzone_gives_zero_not_nan() {
+        let mut pad = Gamepad::new("Pad".to_string());
+        pad.apply(
+            &reading(&[(GamepadAxis::LeftX, 1.0), (GamepadAxis::RightTrigger, 1.0)]),
+            1.0,
+        );
+        assert_eq!(pad.axis(GamepadAxis::LeftX), 0.0);
+        assert_eq!(pad.axis(GamepadAxis::RightTrigger), 0.0);
+        assert_eq!(rescale(1.0, 1.5), 0.0);
+    }
+}
@@ -0,0 +1,144 @@
+/// Declares a field-less input enum along with the list of its variants and
+/// their names, used to read and write bindings.
+macro_rules! named_enum {
+    ($(#[$meta:meta])* pub enum $name:ident { $($variant:ident,)* }) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $($variant,)*
+        }
+
+        impl $name {
+            pub const ALL: &'static [$name] = &[$($name::$variant,)*];
+
+            /// The variant name, e.g. in config files.
+            pub fn name(self) -> &'static str {
+                match self {
+                    $($name::$variant => stringify!($variant),)*
+                }
+            }
+
+            pub fn from_name(name: &str) -> Option<Self> {
+                Self::ALL.iter().copied().find(|variant| variant.name() == name)
+            }
+        }
+    };
+}
+pub(crate) use named_enum;
+
+named_enum! {
+    /// Keyboard keys, named after their US layout position.
+    pub enum Key {
+        A,
+        B,
+        C,
+        D,
+        E,
+        F,
+        G,
+        H,
+        I,
+        J,
+        K,
+        L,
+        M,
+        N,
+        O,
+        P,
+        Q,
+        R,
+        S,
+        T,
+        U,
+        V,
+        W,
+        X,
+        Y,
+        Z,
+        Num0,
+        Num1,
+        Num2,
+        Num3,
+        Num4,
+        Num5,
+        Num6,
+        Num7,
+        Num8,
+        Num9,
+        F1,
+        F2,
+        F3,
+        F4,
+        F5,
+        F6,
+        F7,
+        F8,
+        F9,
+        F10,
+        F11,
+        F12,
+        Escape,
+        Enter,
+        Space,
+        Tab,
+        Backspace,
+        Insert,
+        Delete,
+        Home,
+        End,
+        PageUp,
+        PageDown,
+        Left,
+        Right,
+        Up,
+        Down,
+        LShift,
+        RShift,
+        LControl,
+        RControl,
+        LAlt,
+        RAlt,
+        Minus,
+        Equals,
+        Comma,
+        Period,
+        Slash,
+        Semicolon,
+        Apostrophe,
+        LBracket,
+        RBracket,
+        Backslash,
+        Grave,
+        CapsLock,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    /// Extra buttons, numbered as the windowing library reports them.
+    Other(u16),
+}
+
+impl MouseButton {
+    /// `Left`, `Right`, `Middle`, or the number of any other button.
+    pub fn name(self) -> String {
+        match self {
+            MouseButton::Left => "Left".to_string(),
+            MouseButton::Right => "Right".to_string(),
+            MouseButton::Middle => "Middle".to_string(),
+            MouseButton::Other(n) => n.to_string(),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Left" => Some(MouseButton::Left),
+            "Right" => Some(MouseButton::Right),
+            "Middle" => Some(MouseButton::Middle),
+            _ => name.parse().ok().map(MouseButton::Other),
+        }
+    }
+}
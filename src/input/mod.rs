@@ -0,0 +1,425 @@
+pub mod action_map;
+pub mod gamepad;
+pub mod keys;
+pub mod text;
+
+pub use action_map::{ActionMap, AxisBinding, Binding};
+pub use gamepad::{
+    Gamepad, GamepadAxis, GamepadButton, GamepadEvent, GamepadId, GamepadReading, GamepadSource,
+    SimulatedGamepads,
+};
+pub use keys::{Key, MouseButton};
+pub use text::TextEvent;
+
+use nalgebra_glm as glm;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::Hash;
+use std::time::Duration;
+
+/// Default for `InputState::set_gamepad_deadzone`, enough for worn sticks.
+pub const DEFAULT_GAMEPAD_DEADZONE: f32 = 0.15;
+
+/// Keys, mouse and gamepads as seen by the panel's event loop. Panels
+/// translate their windowing library's events into it, so games only depend
+/// on omak's input types.
+///
+/// Transitions (`just_pressed`, `just_released`) are seen by exactly one
+/// update: the game loop clears them once `Runnable::update` returns.
+#[derive(Clone, Debug)]
+pub struct InputState {
+    keys: Buttons<Key>,
+    mouse_buttons: Buttons<MouseButton>,
+    cursor: Option<glm::Vec2>,
+    scroll: glm::Vec2,
+    gamepads: BTreeMap<GamepadId, Gamepad>,
+    gamepad_events: Vec<GamepadEvent>,
+    gamepad_deadzone: f32,
+    text_events: Vec<TextEvent>,
+    composition: Option<String>,
+}
+
+impl Default for InputState {
+    fn default() -> Self {
+        Self {
+            keys: Buttons::default(),
+            mouse_buttons: Buttons::default(),
+            cursor: None,
+            scroll: glm::Vec2::zeros(),
+            gamepads: BTreeMap::new(),
+            gamepad_events: Vec::new(),
+            gamepad_deadzone: DEFAULT_GAMEPAD_DEADZONE,
+            text_events: Vec::new(),
+            composition: None,
+        }
+    }
+}
+
+impl InputState {
+    pub fn is_pressed(&self, key: Key) -> bool {
+        self.keys.is_held(key)
+    }
+
+    /// Pressed since the previous update. Key repeats do not count.
+    pub fn just_pressed(&self, key: Key) -> bool {
+        self.keys.pressed.contains(&key)
+    }
+
+    /// Released since the previous update.
+    pub fn just_released(&self, key: Key) -> bool {
+        self.keys.released.contains(&key)
+    }
+
+    /// Game time the key has been held for, counted in updates: zero during
+    /// the update that sees it pressed. `None` when the key is up.
+    pub fn held_for(&self, key: Key) -> Option<Duration> {
+        self.keys.held.get(&key).copied()
+    }
+
+    /// Keys held, in no particular order.
+    pub fn pressed_keys(&self) -> impl Iterator<Item = Key> + '_ {
+        self.keys.held.keys().copied()
+    }
+
+    pub fn is_mouse_pressed(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.is_held(button)
+    }
+
+    pub fn mouse_just_pressed(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.pressed.contains(&button)
+    }
+
+    pub fn mouse_just_released(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.released.contains(&button)
+    }
+
+    pub fn mouse_held_for(&self, button: MouseButton) -> Option<Duration> {
+        self.mouse_buttons.held.get(&button).copied()
+    }
+
+    /// Mouse buttons held, in no particular order.
+    pub fn pressed_mouse_buttons(&self) -> impl Iterator<Item = MouseButton> + '_ {
+        self.mouse_buttons.held.keys().copied()
+    }
+
+    /// Cursor position in window pixels, from the top left corner. `None`
+    /// while the cursor is outside the window.
+    pub fn cursor_position(&self) -> Option<glm::Vec2> {
+        self.cursor
+    }
+
+    /// Wheel movement since the previous update, in lines: y is positive
+    /// when scrolling up, x when scrolling right.
+    pub fn scroll(&self) -> glm::Vec2 {
+        self.scroll
+    }
+
+    /// Text typed since the previous update. Unlike keys, it follows the
+    /// keyboard layout, dead keys and input methods.
+    pub fn text_events(&self) -> &[TextEvent] {
+        &self.text_events
+    }
+
+    /// The characters inserted since the previous update, ignoring backspaces.
+    pub fn text(&self) -> String {
+        self.text_events
+            .iter()
+            .filter_map(|event| match event {
+                TextEvent::Insert(text) => Some(text.as_str()),
+                TextEvent::Backspace => None,
+            })
+            .collect()
+    }
+
+    /// Types this update's text into `text`, e.g. a player name being entered.
+    pub fn edit_text(&self, text: &mut String) {
+        text::apply(&self.text_events, text);
+    }
+
+    /// Text being composed in an input method, not committed yet. Text
+    /// fields show it at their caret.
+    pub fn composition(&self) -> Option<&str> {
+        self.composition.as_deref()
+    }
+
+    /// Connected gamepads, by id.
+    pub fn gamepads(&self) -> impl Iterator<Item = (GamepadId, &Gamepad)> {
+        self.gamepads.iter().map(|(id, pad)| (*id, pad))
+    }
+
+    pub fn gamepad(&self, id: GamepadId) -> Option<&Gamepad> {
+        self.gamepads.get(&id)
+    }
+
+    /// Gamepads plugged in or out since the previous update.
+    pub fn gamepad_events(&self) -> &[GamepadEvent] {
+        &self.gamepad_events
+    }
+
+    /// Held on any connected gamepad.
+    pub fn is_gamepad_pressed(&self, button: GamepadButton) -> bool {
+        self.gamepads.values().any(|pad| pad.is_pressed(button))
+    }
+
+    pub fn gamepad_just_pressed(&self, button: GamepadButton) -> bool {
+        self.gamepads.values().any(|pad| pad.just_pressed(button))
+    }
+
+    pub fn gamepad_just_released(&self, button: GamepadButton) -> bool {
+        self.gamepads.values().any(|pad| pad.just_released(button))
+    }
+
+    /// Position of `axis` on the gamepad pushing it furthest, 0 without gamepads.
+    pub fn gamepad_axis(&self, axis: GamepadAxis) -> f32 {
+        self.gamepads
+            .values()
+            .map(|pad| pad.axis(axis))
+            .fold(0.0, |furthest, value| {
+                if value.abs() > furthest.abs() {
+                    value
+                } else {
+                    furthest
+                }
+            })
+    }
+
+    pub fn gamepad_deadzone(&self) -> f32 {
+        self.gamepad_deadzone
+    }
+
+    /// How far sticks and triggers must move, from 0 to 1, before they
+    /// count. Applies from the next poll of the gamepads.
+    pub fn set_gamepad_deadzone(&mut self, deadzone: f32) {
+        self.gamepad_deadzone = deadzone.clamp(0.0, 0.95);
+    }
+
+    pub(crate) fn press(&mut self, key: Key) {
+        self.keys.press(key);
+    }
+
+    pub(crate) fn release(&mut self, key: Key) {
+        self.keys.release(key);
+    }
+
+    pub(crate) fn press_mouse(&mut self, button: MouseButton) {
+        self.mouse_buttons.press(button);
+    }
+
+    pub(crate) fn release_mouse(&mut self, button: MouseButton) {
+        self.mouse_buttons.release(button);
+    }
+
+    pub(crate) fn set_cursor(&mut self, position: Option<glm::Vec2>) {
+        self.cursor = position;
+    }
+
+    pub(crate) fn add_scroll(&mut self, delta: glm::Vec2) {
+        self.scroll += delta;
+    }
+
+    /// Adds typed, committed or pasted text, without its control characters.
+    pub(crate) fn insert_text(&mut self, text: &str) {
+        let text: String = text.chars().filter(|c| !c.is_control()).collect();
+        if text.is_empty() {
+            return;
+        }
+        match self.text_events.last_mut() {
+            Some(TextEvent::Insert(last)) => last.push_str(&text),
+            _ => self.text_events.push(TextEvent::Insert(text)),
+        }
+    }
+
+    /// Text editing done by a key press or repeat: backspace, and pasting
+    /// `clipboard` on Ctrl+V or Shift+Insert.
+    pub(crate) fn type_key(&mut self, key: Key, clipboard: impl FnOnce() -> Option<String>) {
+        let ctrl = self.is_pressed(Key::LControl) || self.is_pressed(Key::RControl);
+        let shift = self.is_pressed(Key::LShift) || self.is_pressed(Key::RShift);
+        match key {
+            Key::Backspace => self.text_events.push(TextEvent::Backspace),
+            Key::V if ctrl => self.paste(clipboard()),
+            Key::Insert if shift => self.paste(clipboard()),
+            _ => {}
+        }
+    }
+
+    fn paste(&mut self, clipboard: Option<String>) {
+        if let Some(text) = clipboard {
+            self.insert_text(&text);
+        }
+    }
+
+    pub(crate) fn set_composition(&mut self, composition: Option<String>) {
+        self.composition = composition.filter(|text| !text.is_empty());
+    }
+
+    /// Takes in a poll of the panel's `GamepadSource`.
+    pub(crate) fn update_gamepads(&mut self, readings: Vec<(GamepadId, GamepadReading)>) {
+        let connected: HashSet<GamepadId> = readings.iter().map(|(id, _)| *id).collect();
+        let events = &mut self.gamepad_events;
+        self.gamepads.retain(|id, _| {
+            let keep = connected.contains(id);
+            if !keep {
+                events.push(GamepadEvent::Disconnected(*id));
+            }
+            keep
+        });
+        for (id, reading) in readings {
+            let pad = self.gamepads.entry(id).or_insert_with(|| {
+                events.push(GamepadEvent::Connected(id));
+                Gamepad::new(reading.name.clone())
+            });
+            pad.apply(&reading, self.gamepad_deadzone);
+        }
+    }
+
+    /// Releases keys and mouse buttons, e.g. when the window loses focus and
+    /// the release events would never come.
+    pub(crate) fn release_all(&mut self) {
+        self.keys.release_all();
+        self.mouse_buttons.release_all();
+    }
+
+    /// Called by the game loop after each update of `dt`.
+    pub(crate) fn end_update(&mut self, dt: Duration) {
+        self.keys.end_update(dt);
+        self.mouse_buttons.end_update(dt);
+        for pad in self.gamepads.values_mut() {
+            pad.end_update(dt);
+        }
+        self.gamepad_events.clear();
+        self.text_events.clear();
+        self.scroll = glm::Vec2::zeros();
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Buttons<T> {
+    held: HashMap<T, Duration>,
+    pressed: HashSet<T>,
+    released: HashSet<T>,
+}
+
+impl<T> Default for Buttons<T> {
+    fn default() -> Self {
+        Self {
+            held: HashMap::new(),
+            pressed: HashSet::new(),
+            released: HashSet::new(),
+        }
+    }
+}
+
+impl<T: Copy + Eq + Hash> Buttons<T> {
+    fn is_held(&self, button: T) -> bool {
+        self.held.contains_key(&button)
+    }
+
+    fn press(&mut self, button: T) {
+        if !self.is_held(button) {
+            self.held.insert(button, Duration::ZERO);
+            self.pressed.insert(button);
+        }
+    }
+
+    fn release(&mut self, button: T) {
+        if self.held.remove(&button).is_some() {
+            self.released.insert(button);
+        }
+    }
+
+    fn release_all(&mut self) {
+        self.released.extend(self.held.drain().map(|(button, _)| button));
+    }
+
+    fn end_update(&mut self, dt: Duration) {
+        self.pressed.clear();
+        self.released.clear();
+        for held_for in self.held.values_mut() {
+            *held_for += dt;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gamepads_connect_and_disconnect_with_the_source() {
+        let pads = SimulatedGamepads::new();
+        let mut source = pads.clone();
+        let mut input = InputState::default();
+
+        pads.connect(GamepadId(0), "Pad");
+        pads.press(GamepadId(0), GamepadButton::Start);
+        input.update_gamepads(source.poll());
+        assert_eq!(
+            input.gamepad_events(),
+            [GamepadEvent::Connected(GamepadId(0))]
+        );
+        assert_eq!(input.gamepad(GamepadId(0)).unwrap().name(), "Pad");
+        assert!(input.gamepad_just_pressed(GamepadButton::Start));
+
+        input.end_update(Duration::from_millis(16));
+        input.update_gamepads(source.poll());
+        assert!(input.gamepad_events().is_empty());
+        assert!(input.is_gamepad_pressed(GamepadButton::Start));
+        assert!(!input.gamepad_just_pressed(GamepadButton::Start));
+
+        input.end_update(Duration::from_millis(16));
+        pads.disconnect(GamepadId(0));
+        input.update_gamepads(source.poll());
+        assert_eq!(
+            input.gamepad_events(),
+            [GamepadEvent::Disconnected(GamepadId(0))]
+        );
+        assert!(input.gamepad(GamepadId(0)).is_none());
+        assert!(!input.is_gamepad_pressed(GamepadButton::Start));
+    }
+
+    #[test]
+    fn typing_merges_inserts_and_drops_control_characters() {
+        let mut input = InputState::default();
+        input.insert_text("ab");
+        input.insert_text("\r");
+        input.insert_text("c\td");
+        input.type_key(Key::Backspace, || None);
+        input.insert_text("é");
+        assert_eq!(
+            input.text_events(),
+            [
+                TextEvent::Insert("abcd".to_string()),
+                TextEvent::Backspace,
+                TextEvent::Insert("é".to_string()),
+            ]
+        );
+        assert_eq!(input.text(), "abcdé");
+
+        let mut name = "x".to_string();
+        text::apply(input.text_events(), &mut name);
+        assert_eq!(name, "xabcé");
+
+        input.end_update(Duration::from_millis(16));
+        assert!(input.text_events().is_empty());
+    }
+
+    #[test]
+    fn paste_needs_ctrl_v_or_shift_insert() {
+        let clipboard = || Some("pasted".to_string());
+        let mut input = InputState::default();
+        input.type_key(Key::V, clipboard);
+        input.type_key(Key::Insert, clipboard);
+        // other keys do not edit text
+        input.type_key(Key::Delete, clipboard);
+        input.type_key(Key::Enter, clipboard);
+        assert!(input.text_events().is_empty());
+
+        input.press(Key::LControl);
+        input.type_key(Key::V, clipboard);
+        input.release(Key::LControl);
+        input.press(Key::RShift);
+        input.type_key(Key::Insert, clipboard);
+        // an empty clipboard pastes nothing
+        input.type_key(Key::Insert, || None);
+        assert_eq!(input.text(), "pastedpasted");
+    }
+}
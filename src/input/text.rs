@@ -0,0 +1,21 @@
+/// Text entry, in the order it happened, for name fields and consoles.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TextEvent {
+    /// Characters typed, committed by an input method or pasted. Never holds
+    /// control characters: Enter, Tab and the like are read as keys.
+    Insert(String),
+    /// Backspace pressed, or repeated while held.
+    Backspace,
+}
+
+/// Applies `events` to `text`, editing at its end.
+pub fn apply(events: &[TextEvent], text: &mut String) {
+    for event in events {
+        match event {
+            TextEvent::Insert(inserted) => text.push_str(inserted),
+            TextEvent::Backspace => {
+                text.pop();
+            }
+        }
+    }
+}
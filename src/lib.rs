@@ -1,2 +1,6 @@
+pub mod error;
+pub mod input;
 pub mod panels;
 pub mod renderer;
+
+pub use error::{Error, Result};
@@ -1,14 +1,71 @@
-use std::collections::HashSet;
-use winit::event::VirtualKeyCode as Key;
-
+use crate::error::Result;
+use crate::input::InputState;
+use crate::panels::frame_stats::FrameStats;
+use crate::panels::window_config::WindowConfig;
 use crate::renderer::Renderer;
+use nalgebra_glm as glm;
 
 pub trait GamePanel {
-    fn build(width: u32, height: u32) -> Self;
+    fn build(width: u32, height: u32) -> Self
+    where
+        Self: Sized,
+    {
+        Self::try_build(width, height).unwrap_or_else(|e| panic!("{}", e))
+    }
+    /// Like `build`, but reports a missing GL context or asset instead of panicking.
+    fn try_build(width: u32, height: u32) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        Self::try_build_with(WindowConfig::init(width, height))
+    }
+    fn build_with(config: WindowConfig) -> Self
+    where
+        Self: Sized,
+    {
+        Self::try_build_with(config).unwrap_or_else(|e| panic!("{}", e))
+    }
+    /// Builds the panel with the title, size, context and mode of `config`.
+    fn try_build_with(config: WindowConfig) -> Result<Self>
+    where
+        Self: Sized;
     fn run(self, runnable: impl Runnable + 'static);
     fn get_renderer(&mut self) -> &mut Renderer;
-    fn get_keys(&self) -> &HashSet<Key>;
+    /// Keys, mouse and gamepads, updated before each frame.
+    fn get_input(&self) -> &InputState;
+    fn get_input_mut(&mut self) -> &mut InputState;
+    /// Cursor position in screen space, i.e. in pixels of the virtual
+    /// resolution when there is one.
+    fn cursor_screen(&mut self) -> Option<glm::Vec2> {
+        let cursor = self.get_input().cursor_position()?;
+        self.get_renderer().window_to_screen(cursor)
+    }
+    /// Cursor position in world coordinates, through the renderer's camera.
+    fn cursor_world(&mut self) -> Option<glm::Vec2> {
+        let cursor = self.cursor_screen()?;
+        Some(self.get_renderer().camera.screen_to_world(cursor))
+    }
+    /// Text on the system clipboard. Panels whose windowing library cannot
+    /// reach it keep a clipboard of their own, shared with `set_clipboard`.
+    fn get_clipboard(&mut self) -> Option<String>;
+    fn set_clipboard(&mut self, text: &str);
+    /// Tells the panel a text field has focus, so it turns on the input
+    /// method for composing text, see `InputState::composition`. Off by
+    /// default: the input method would swallow keys the game plays with.
+    fn set_text_input(&mut self, _enabled: bool) {}
+    /// Timings of the last frames run.
+    fn get_frame_stats(&self) -> &FrameStats;
+    fn get_frame_stats_mut(&mut self) -> &mut FrameStats;
+    /// Draws FPS and frame times in the top left corner, over the game.
+    fn show_frame_stats(&mut self, show: bool) {
+        self.get_frame_stats_mut().set_overlay(show);
+    }
 }
+/// A game driven by a panel's `GameLoop`.
 pub trait Runnable {
-    fn run(&mut self, panel: &mut impl GamePanel);
+    /// Advances the game by `dt` seconds, at the panel's fixed update rate.
+    fn update(&mut self, panel: &mut impl GamePanel, dt: f32);
+    /// Draws the game, once per frame. `alpha`, in [0, 1), is how far the
+    /// clock is between the last update and the next, for interpolating.
+    fn render(&mut self, panel: &mut impl GamePanel, alpha: f32);
 }
@@ -0,0 +1,200 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::renderer::camera::Space;
+use crate::renderer::layer::Layer;
+use crate::renderer::Renderer;
+
+/// Frames the statistics are computed over.
+const WINDOW: usize = 120;
+const OVERLAY_SIZE: f32 = 12.0;
+const OVERLAY_LINE_HEIGHT: f32 = 16.0;
+
+/// Timings of a single frame.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FrameSample {
+    /// Wall clock time since the previous frame.
+    pub frame: Duration,
+    /// Spent in `Runnable::update`, all updates of the frame together.
+    pub update: Duration,
+    /// Spent in `Runnable::render` and handing the frame to the backend.
+    pub render: Duration,
+}
+
+/// Rolling statistics over the last frames run by a panel's `GameLoop`.
+#[derive(Clone, Debug, Default)]
+pub struct FrameStats {
+    samples: VecDeque<FrameSample>,
+    overlay: bool,
+}
+
+impl FrameStats {
+    pub(crate) fn record(&mut self, sample: FrameSample) {
+        if self.samples.len() == WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// Frames in the window, oldest first.
+    pub fn samples(&self) -> impl Iterator<Item = &FrameSample> {
+        self.samples.iter()
+    }
+
+    /// Frames per second over the window, 0 until a frame took any time.
+    pub fn fps(&self) -> f32 {
+        let total: Duration = self.samples.iter().map(|s| s.frame).sum();
+        if total.is_zero() {
+            return 0.0;
+        }
+        self.samples.len() as f32 / total.as_secs_f32()
+    }
+
+    pub fn frame_time_min(&self) -> Duration {
+        self.samples.iter().map(|s| s.frame).min().unwrap_or_default()
+    }
+
+    pub fn frame_time_avg(&self) -> Duration {
+        average(self.samples.iter().map(|s| s.frame))
+    }
+
+    pub fn frame_time_max(&self) -> Duration {
+        self.samples.iter().map(|s| s.frame).max().unwrap_or_default()
+    }
+
+    /// Frame time that `percentile` percent of the frames stay under,
+    /// e.g. 99.0 for the slowest frames without the outliers.
+    pub fn frame_time_percentile(&self, percentile: f32) -> Duration {
+        let mut times: Vec<Duration> = self.samples.iter().map(|s| s.frame).collect();
+        if times.is_empty() {
+            return Duration::ZERO;
+        }
+        times.sort_unstable();
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * times.len() as f32).ceil() as usize;
+        times[rank.clamp(1, times.len()) - 1]
+    }
+
+    /// Average time per frame spent updating.
+    pub fn update_time(&self) -> Duration {
+        average(self.samples.iter().map(|s| s.update))
+    }
+
+    /// Average time per frame spent rendering.
+    pub fn render_time(&self) -> Duration {
+        average(self.samples.iter().map(|s| s.render))
+    }
+
+    pub fn overlay_shown(&self) -> bool {
+        self.overlay
+    }
+
+    /// Whether the panel draws the statistics on top of the game.
+    pub fn set_overlay(&mut self, show: bool) {
+        self.overlay = show;
+    }
+
+    /// The statistics as the overlay shows them, one line per entry.
+    pub(crate) fn overlay_lines(&self) -> Vec<String> {
+        let ms = |time: Duration| time.as_secs_f32() * 1000.0;
+        vec![
+            format!(
+                "FPS {:.0} frame {:.1}/{:.1}/{:.1}ms p99 {:.1}ms",
+                self.fps(),
+                ms(self.frame_time_min()),
+                ms(self.frame_time_avg()),
+                ms(self.frame_time_max()),
+                ms(self.frame_time_percentile(99.0)),
+            ),
+            format!(
+                "update {:.2}ms render {:.2}ms",
+                ms(self.update_time()),
+                ms(self.render_time()),
+            ),
+        ]
+    }
+}
+
+/// Prints `lines` in the top left corner of the screen.
+pub(crate) fn draw_overlay(renderer: &mut Renderer, lines: &[String]) {
+    let (space, layer) = (renderer.space(), renderer.layer());
+    renderer.set_space(Space::Screen);
+    renderer.set_layer(Layer::Ui);
+    for (i, line) in lines.iter().enumerate() {
+        renderer.println(8.0, 8.0 + i as f32 * OVERLAY_LINE_HEIGHT, OVERLAY_SIZE, line);
+    }
+    renderer.set_space(space);
+    renderer.set_layer(layer);
+}
+
+fn average(times: impl ExactSizeIterator<Item = Duration>) -> Duration {
+    let count = times.len() as u32;
+    if count == 0 {
+        return Duration::ZERO;
+    }
+    times.sum::<Duration>() / count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    fn stats(frame_times: impl IntoIterator<Item = u64>) -> FrameStats {
+        let mut stats = FrameStats::default();
+        for millis in frame_times {
+            stats.record(FrameSample {
+                frame: ms(millis),
+                update: ms(millis / 4),
+                render: ms(millis / 2),
+            });
+        }
+        stats
+    }
+
+    #[test]
+    fn an_empty_window_reads_zero() {
+        let stats = stats([]);
+        assert_eq!(stats.fps(), 0.0);
+        assert_eq!(stats.frame_time_min(), Duration::ZERO);
+        assert_eq!(stats.frame_time_avg(), Duration::ZERO);
+        assert_eq!(stats.frame_time_max(), Duration::ZERO);
+        assert_eq!(stats.frame_time_percentile(99.0), Duration::ZERO);
+        assert_eq!(stats.update_time(), Duration::ZERO);
+        // frames that took no time at all do not divide by zero either
+        assert_eq!(self::stats([0, 0]).fps(), 0.0);
+        assert_eq!(
+            stats.overlay_lines(),
+            [
+                "FPS 0 frame 0.0/0.0/0.0ms p99 0.0ms",
+                "update 0.00ms render 0.00ms"
+            ]
+        );
+    }
+
+    #[test]
+    fn times_are_read_over_the_window() {
+        // 1 to 100 ms, then a 1 s hitch
+        let stats = stats((1..=100).chain([1000]));
+        assert_eq!(stats.frame_time_min(), ms(1));
+        assert_eq!(stats.frame_time_max(), ms(1000));
+        assert_eq!(stats.frame_time_avg(), ms(5050 + 1000) / 101);
+        assert_eq!(stats.frame_time_percentile(50.0), ms(51));
+        assert_eq!(stats.frame_time_percentile(99.0), ms(100));
+        assert_eq!(stats.frame_time_percentile(100.0), ms(1000));
+        assert_eq!(stats.frame_time_percentile(0.0), ms(1));
+        assert!((stats.fps() - 101.0 / 6.05).abs() < 1e-3);
+        // halves rounded down: 2500 ms, and 500 for the hitch
+        assert_eq!(stats.render_time(), ms(3000) / 101);
+    }
+
+    #[test]
+    fn the_oldest_frames_leave_the_window() {
+        let stats = stats((1..=WINDOW as u64 + 30).map(|i| if i <= 30 { 500 } else { 10 }));
+        assert_eq!(stats.samples().count(), WINDOW);
+        assert_eq!(stats.frame_time_max(), ms(10));
+        assert!((stats.fps() - 100.0).abs() < 1e-3);
+    }
+}
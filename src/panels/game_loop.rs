@@ -0,0 +1,193 @@
+use std::time::{Duration, Instant};
+
+use crate::panels::common::{GamePanel, Runnable};
+use crate::panels::frame_stats::{self, FrameSample};
+use crate::panels::window_config::WindowConfig;
+
+/// Updates per second the panels run at unless told otherwise.
+pub const DEFAULT_UPDATE_RATE: f64 = 60.0;
+/// Longest frame the loop catches up on. A slower frame (a breakpoint, a
+/// dragged window) is cut short instead of piling up updates that make the
+/// next frame even slower.
+const MAX_FRAME_TIME: Duration = Duration::from_millis(250);
+
+/// Fixed timestep loop shared by the panels: the game is updated at a steady
+/// rate whatever the frame rate, and rendered once per frame with how far the
+/// clock got towards the next update.
+pub struct GameLoop {
+    timestep: Duration,
+    accumulator: Duration,
+    last_frame: Option<Instant>,
+    frame_interval: Option<Duration>,
+}
+
+impl GameLoop {
+    /// Loop running `update_rate` updates per second, `DEFAULT_UPDATE_RATE`
+    /// when that is not a positive number.
+    pub fn new(update_rate: f64) -> Self {
+        let timestep = interval(update_rate).unwrap_or_else(|| {
+            log::warn!(
+                "Cannot run {} updates per second, running {}",
+                update_rate,
+                DEFAULT_UPDATE_RATE
+            );
+            Duration::from_secs_f64(1.0 / DEFAULT_UPDATE_RATE)
+        });
+        Self {
+            timestep,
+            accumulator: Duration::ZERO,
+            last_frame: None,
+            frame_interval: None,
+        }
+    }
+
+    /// Loop at the update rate of `config`, its frames spaced as it asks.
+    pub(crate) fn from_config(config: &WindowConfig) -> Self {
+        Self {
+            frame_interval: config.frame_interval(),
+            ..Self::new(config.update_rate)
+        }
+    }
+
+    /// Time each update advances the game by.
+    pub fn timestep(&self) -> Duration {
+        self.timestep
+    }
+
+    /// When the next frame is due, `None` when frames are not held back or
+    /// none has run yet.
+    pub fn next_frame(&self) -> Option<Instant> {
+        Some(self.last_frame? + self.frame_interval?)
+    }
+
+    /// Wall clock time since the previous call, zero on the first one.
+    pub fn elapsed(&mut self) -> Duration {
+        let now = Instant::now();
+        let elapsed = self
+            .last_frame
+            .map_or(Duration::ZERO, |last| now.duration_since(last));
+        self.last_frame = Some(now);
+        elapsed
+    }
+
+    /// Runs one frame: as many updates as `elapsed` covers, then a render.
+    /// Its timings go to the panel's `FrameStats`.
+    pub fn frame<P: GamePanel>(
+        &mut self,
+        panel: &mut P,
+        runnable: &mut impl Runnable,
+        elapsed: Duration,
+    ) {
+        if elapsed > MAX_FRAME_TIME {
+            log::debug!("Frame took {:?}, skipping ahead", elapsed);
+        }
+        self.accumulator += elapsed.min(MAX_FRAME_TIME);
+
+        let dt = self.timestep.as_secs_f32();
+        let update_start = Instant::now();
+        while self.accumulator >= self.timestep {
+            runnable.update(panel, dt);
+            panel.get_input_mut().end_update(self.timestep);
+            self.accumulator -= self.timestep;
+        }
+        let update = update_start.elapsed();
+        let alpha = self.accumulator.as_secs_f32() / dt;
+
+        let render_start = Instant::now();
+        panel.get_renderer().clear();
+        runnable.render(panel, alpha);
+        if panel.get_frame_stats().overlay_shown() {
+            // the stats of the previous frames: this one is still running
+            let lines = panel.get_frame_stats().overlay_lines();
+            frame_stats::draw_overlay(panel.get_renderer(), &lines);
+        }
+        panel.get_renderer().flush();
+        let render = render_start.elapsed();
+
+        panel.get_frame_stats_mut().record(FrameSample {
+            frame: elapsed,
+            update,
+            render,
+        });
+    }
+}
+
+/// Time between two of `rate` events per second, `None` unless `rate` is
+/// positive and small enough to leave them apart.
+pub(crate) fn interval(rate: f64) -> Option<Duration> {
+    Duration::try_from_secs_f64(1.0 / rate)
+        .ok()
+        .filter(|interval| !interval.is_zero())
+}
+
+impl Default for GameLoop {
+    fn default() -> Self {
+        Self::new(DEFAULT_UPDATE_RATE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::panels::headless_panel::WindowHeadless;
+
+    /// Counts the updates since the last render, and what each render saw.
+    #[derive(Default)]
+    struct Counter {
+        updates: usize,
+        dt: f32,
+        /// Updates and alpha of each frame.
+        frames: Vec<(usize, f32)>,
+    }
+
+    impl Runnable for Counter {
+        fn update(&mut self, _panel: &mut impl GamePanel, dt: f32) {
+            self.updates += 1;
+            self.dt = dt;
+        }
+
+        fn render(&mut self, _panel: &mut impl GamePanel, alpha: f32) {
+            self.frames.push((std::mem::take(&mut self.updates), alpha));
+        }
+    }
+
+    fn run(game_loop: &mut GameLoop, frame_times: &[u64]) -> Counter {
+        let mut panel = WindowHeadless::build(64, 64);
+        let mut counter = Counter::default();
+        for millis in frame_times {
+            game_loop.frame(&mut panel, &mut counter, Duration::from_millis(*millis));
+        }
+        counter
+    }
+
+    #[test]
+    fn frames_run_the_updates_their_time_covers() {
+        // 20 ms updates
+        let counter = run(&mut GameLoop::new(50.0), &[30, 10, 5, 0, 45, 20]);
+        assert_eq!(counter.dt, 0.02);
+        let expected = [(1, 0.5), (1, 0.0), (0, 0.25), (0, 0.25), (2, 0.5), (1, 0.5)];
+        assert_eq!(counter.frames.len(), expected.len());
+        for (frame, (updates, alpha)) in counter.frames.iter().zip(expected) {
+            assert_eq!(frame.0, updates);
+            assert!((frame.1 - alpha).abs() < 1e-4, "{:?}", counter.frames);
+        }
+    }
+
+    #[test]
+    fn slow_frames_catch_up_on_250_ms_at_most() {
+        let counter = run(&mut GameLoop::new(50.0), &[5, 10_000, 20]);
+        let updates: Vec<usize> = counter.frames.iter().map(|frame| frame.0).collect();
+        // 5 + 250 ms, then 15 + 20 ms
+        assert_eq!(updates, [0, 12, 1]);
+        assert!((counter.frames[1].1 - 0.75).abs() < 1e-4);
+    }
+
+    #[test]
+    fn rates_that_are_not_positive_run_at_the_default() {
+        let default = GameLoop::default().timestep();
+        for rate in [0.0, -60.0, f64::NAN, f64::INFINITY] {
+            assert_eq!(GameLoop::new(rate).timestep(), default, "{}", rate);
+        }
+        assert_eq!(interval(4.0), Some(Duration::from_millis(250)));
+    }
+}
@@ -0,0 +1,95 @@
+use crate::input::{GamepadAxis, GamepadButton, GamepadId, GamepadReading, GamepadSource};
+use std::collections::{HashMap, HashSet};
+
+/// Gamepads read through gilrs, as winit has no gamepad support. Pads
+/// plugged in while the game runs show up on the next poll.
+pub(crate) struct GilrsGamepads {
+    gilrs: gilrs::Gilrs,
+}
+
+impl GilrsGamepads {
+    /// `None`, with a warning, when the platform's gamepads cannot be read.
+    pub(crate) fn try_new() -> Option<Self> {
+        // the input state applies its own deadzone
+        match gilrs::GilrsBuilder::new()
+            .with_default_filters(false)
+            .build()
+        {
+            Ok(gilrs) => Some(Self { gilrs }),
+            Err(e) => {
+                log::warn!("Gamepads are unavailable: {}", e);
+                None
+            }
+        }
+    }
+}
+
+impl GamepadSource for GilrsGamepads {
+    fn poll(&mut self) -> Vec<(GamepadId, GamepadReading)> {
+        // the events update the pads' state and bring in new pads
+        while self.gilrs.next_event().is_some() {}
+        self.gilrs
+            .gamepads()
+            .map(|(id, pad)| {
+                let mut buttons: HashSet<GamepadButton> = GAMEPAD_BUTTONS
+                    .iter()
+                    .filter(|(button, _)| pad.is_pressed(*button))
+                    .map(|(_, button)| *button)
+                    .collect();
+                // some pads report their d-pad as a hat, read as two axes
+                let (dpad_x, dpad_y) =
+                    (pad.value(gilrs::Axis::DPadX), pad.value(gilrs::Axis::DPadY));
+                for (pressed, button) in [
+                    (dpad_x < -0.5, GamepadButton::DPadLeft),
+                    (dpad_x > 0.5, GamepadButton::DPadRight),
+                    (dpad_y > 0.5, GamepadButton::DPadUp),
+                    (dpad_y < -0.5, GamepadButton::DPadDown),
+                ] {
+                    if pressed {
+                        buttons.insert(button);
+                    }
+                }
+                let trigger = |button| pad.button_data(button).map_or(0.0, |data| data.value());
+                let axes = HashMap::from([
+                    (GamepadAxis::LeftX, pad.value(gilrs::Axis::LeftStickX)),
+                    // gilrs points up, the input state down like the screen
+                    (GamepadAxis::LeftY, -pad.value(gilrs::Axis::LeftStickY)),
+                    (GamepadAxis::RightX, pad.value(gilrs::Axis::RightStickX)),
+                    (GamepadAxis::RightY, -pad.value(gilrs::Axis::RightStickY)),
+                    (
+                        GamepadAxis::LeftTrigger,
+                        trigger(gilrs::Button::LeftTrigger2),
+                    ),
+                    (
+                        GamepadAxis::RightTrigger,
+                        trigger(gilrs::Button::RightTrigger2),
+                    ),
+                ]);
+                let reading = GamepadReading {
+                    name: pad.name().to_string(),
+                    buttons,
+                    axes,
+                };
+                (GamepadId(usize::from(id) as u32), reading)
+            })
+            .collect()
+    }
+}
+
+const GAMEPAD_BUTTONS: [(gilrs::Button, GamepadButton); 15] = [
+    (gilrs::Button::South, GamepadButton::South),
+    (gilrs::Button::East, GamepadButton::East),
+    (gilrs::Button::West, GamepadButton::West),
+    (gilrs::Button::North, GamepadButton::North),
+    (gilrs::Button::LeftTrigger, GamepadButton::LeftBumper),
+    (gilrs::Button::RightTrigger, GamepadButton::RightBumper),
+    (gilrs::Button::Select, GamepadButton::Back),
+    (gilrs::Button::Start, GamepadButton::Start),
+    (gilrs::Button::Mode, GamepadButton::Guide),
+    (gilrs::Button::LeftThumb, GamepadButton::LeftThumb),
+    (gilrs::Button::RightThumb, GamepadButton::RightThumb),
+    (gilrs::Button::DPadUp, GamepadButton::DPadUp),
+    (gilrs::Button::DPadRight, GamepadButton::DPadRight),
+    (gilrs::Button::DPadDown, GamepadButton::DPadDown),
+    (gilrs::Button::DPadLeft, GamepadButton::DPadLeft),
+];
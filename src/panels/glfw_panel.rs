@@ -1,105 +1,484 @@
 use std::sync::mpsc::Receiver;
+use std::time::Instant;
 
+use crate::error::{Error, Result};
+use crate::input::{
+    GamepadAxis, GamepadButton, GamepadId, GamepadReading, GamepadSource, InputState, Key,
+    MouseButton,
+};
 use crate::panels::common::{GamePanel, Runnable};
+use crate::panels::frame_stats::FrameStats;
+use crate::panels::game_loop::GameLoop;
+use crate::panels::window_config::{WindowConfig, WindowMode, WindowPosition};
 use crate::renderer::Renderer;
-use gl::types::*;
 use glfw::{self, Glfw};
-use glfw::{Action, Context, Key, Window, WindowEvent};
+use glfw::{Action, Context, Window, WindowEvent};
+use nalgebra_glm as glm;
 
 pub struct WindowGlfw {
+    /// Size of the framebuffer in pixels, kept up to date when the window
+    /// is resized.
     pub width: u32,
     pub height: u32,
+    // dropped before the window, which its GL objects belong to
+    renderer: Renderer,
     window: Window,
     events: Receiver<(f64, WindowEvent)>,
     glfw: Glfw,
-    keys: [bool; 1024],
-    renderer: Renderer,
+    input: InputState,
+    gamepads: Box<dyn GamepadSource>,
+    game_loop: GameLoop,
+    frame_stats: FrameStats,
 }
 impl WindowGlfw {
+    /// Replaces the joysticks GLFW maps as gamepads, e.g. with a
+    /// `SimulatedGamepads` to replay recorded input.
+    pub fn with_gamepad_source(mut self, source: Box<dyn GamepadSource>) -> Self {
+        self.gamepads = source;
+        self
+    }
+
     fn process_events(&mut self) {
         for (_, event) in glfw::flush_messages(&self.events) {
             match event {
-                WindowEvent::FramebufferSize(w, h) => unsafe { gl::Viewport(0, 0, w, h) },
-                WindowEvent::Key(Key::Escape, _, Action::Press, _) => {
+                WindowEvent::FramebufferSize(w, h) => {
+                    (self.width, self.height) = (w as u32, h as u32);
+                    self.renderer.resize(self.width, self.height);
+                }
+                WindowEvent::Key(glfw::Key::Escape, _, Action::Press, _) => {
                     self.window.set_should_close(true)
                 }
-                WindowEvent::Key(_, KEY, Action::Press, _) => {
-                    self.keys = [false; 1024];
-                    self.keys[KEY as usize] = true;
+                WindowEvent::Key(key, _, action, _) => {
+                    if let Some(key) = translate_key(key) {
+                        match action {
+                            Action::Press => self.input.press(key),
+                            Action::Release => self.input.release(key),
+                            Action::Repeat => {}
+                        }
+                        if action != Action::Release {
+                            let window = &self.window;
+                            self.input.type_key(key, || window.get_clipboard_string());
+                        }
+                    }
+                }
+                WindowEvent::Char(c) => self.input.insert_text(c.encode_utf8(&mut [0; 4])),
+                WindowEvent::MouseButton(button, action, _) => {
+                    let button = translate_mouse_button(button);
+                    match action {
+                        Action::Press => self.input.press_mouse(button),
+                        Action::Release => self.input.release_mouse(button),
+                        Action::Repeat => {}
+                    }
                 }
-                WindowEvent::Key(_, KEY, Action::Release, _) => {
-                    self.keys[KEY as usize] = false;
+                WindowEvent::Focus(false) => self.input.release_all(),
+                WindowEvent::CursorPos(x, y) => {
+                    let cursor = to_framebuffer(
+                        glm::vec2(x as f32, y as f32),
+                        self.window.get_size(),
+                        self.window.get_framebuffer_size(),
+                    );
+                    self.input.set_cursor(Some(cursor))
                 }
+                WindowEvent::CursorEnter(false) => self.input.set_cursor(None),
+                WindowEvent::Scroll(x, y) => self.input.add_scroll(glm::vec2(x as f32, y as f32)),
                 _ => {}
             }
         }
+        self.input.update_gamepads(self.gamepads.poll());
     }
 }
 
 impl GamePanel for WindowGlfw {
-    fn build(width: u32, height: u32) -> Self {
+    fn try_build_with(config: WindowConfig) -> Result<Self> {
         env_logger::init();
-        let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS).unwrap();
-        glfw.window_hint(glfw::WindowHint::ContextVersion(3, 3));
+        let icon = config.load_icon()?;
+        let mut glfw =
+            glfw::init(glfw::FAIL_ON_ERRORS).map_err(|e| Error::Context(format!("{:?}", e)))?;
+        let (major, minor) = config.gl_version;
+        glfw.window_hint(glfw::WindowHint::ContextVersion(major as u32, minor as u32));
         glfw.window_hint(glfw::WindowHint::OpenGlProfile(
             glfw::OpenGlProfileHint::Core,
         ));
         // #[cfg(target_os = "macos")]
         // glfw.window_hint(glfw::WindowHint::OpenGlForwardCompat(true));
+        glfw.window_hint(glfw::WindowHint::Resizable(config.resizable));
+        glfw.window_hint(glfw::WindowHint::Samples(config.samples.map(u32::from)));
 
         let (mut window, events) = glfw
-            .create_window(width, height, "Omak", glfw::WindowMode::Windowed)
-            .expect("Failed to build GLFW window");
+            .with_primary_monitor(|glfw, monitor| create_window(glfw, monitor, &config))
+            .ok_or_else(|| Error::Context("Failed to build GLFW window".to_string()))?;
 
         window.make_current();
+        glfw.set_swap_interval(if config.vsync {
+            glfw::SwapInterval::Sync(1)
+        } else {
+            glfw::SwapInterval::None
+        });
+        if let Some(icon) = icon {
+            let (width, height) = icon.dimensions();
+            window.set_icon_from_pixels(vec![glfw::PixelImage {
+                width,
+                height,
+                pixels: icon
+                    .pixels()
+                    .map(|pixel| u32::from_ne_bytes(pixel.0))
+                    .collect(),
+            }]);
+        }
         window.set_key_polling(true);
+        window.set_char_polling(true);
+        window.set_mouse_button_polling(true);
+        window.set_focus_polling(true);
+        window.set_cursor_pos_polling(true);
+        window.set_cursor_enter_polling(true);
+        window.set_scroll_polling(true);
         window.set_framebuffer_size_polling(true);
-        // glfw.set_swap_interval(glfw::SwapInterval::None);
         // ---------------------------------------
         gl::load_with(|symbol| window.get_proc_address(symbol) as *const _);
 
-        Self {
+        let (width, height) = window.get_framebuffer_size();
+        let (width, height) = (width as u32, height as u32);
+        let mut renderer = Renderer::try_new(width, height)?;
+        renderer.set_virtual_resolution(config.virtual_resolution);
+        Ok(Self {
             width,
             height,
+            renderer,
             window,
             events,
+            input: InputState::default(),
+            gamepads: Box::new(JoystickGamepads { glfw: glfw.clone() }),
             glfw,
-            keys: [false; 1024],
-            renderer: Renderer::new(width, height),
-        }
+            game_loop: GameLoop::from_config(&config),
+            frame_stats: FrameStats::default(),
+        })
     }
 
     fn run(mut self, mut runnable: impl Runnable) {
-        let mut prev_time = self.glfw.get_time();
-        let mut frame_count = 0;
-
+        let mut game_loop = std::mem::take(&mut self.game_loop);
         while !self.window.should_close() {
-            let current_time = self.glfw.get_time();
-            frame_count += 1;
-            if current_time - prev_time >= 1.0 {
-                // println!("FPS: {}", frame_count);
-                frame_count = 0;
-                prev_time = current_time;
-            }
             self.process_events();
 
             //--------------------------
 
-            self.renderer.clear();
-            runnable.run(&mut self);
+            let elapsed = game_loop.elapsed();
+            game_loop.frame(&mut self, &mut runnable, elapsed);
 
             //--------------------------
 
             self.window.swap_buffers();
-            self.glfw.poll_events();
+            match game_loop.next_frame() {
+                // sleeps until the frame is due, woken early by input
+                Some(at) => {
+                    let wait = at.saturating_duration_since(Instant::now());
+                    self.glfw.wait_events_timeout(wait.as_secs_f64());
+                }
+                None => self.glfw.poll_events(),
+            }
         }
+        drop(runnable);
+        drop(self);
+        crate::renderer::report_live_objects();
     }
 
     fn get_renderer(&mut self) -> &mut Renderer {
         &mut self.renderer
     }
 
-    fn get_keys(&self) -> &[bool] {
-        &self.keys[..]
+    fn get_input(&self) -> &InputState {
+        &self.input
+    }
+
+    fn get_input_mut(&mut self) -> &mut InputState {
+        &mut self.input
+    }
+
+    fn get_clipboard(&mut self) -> Option<String> {
+        self.window.get_clipboard_string()
+    }
+
+    fn set_clipboard(&mut self, text: &str) {
+        self.window.set_clipboard_string(text);
+    }
+
+    fn get_frame_stats(&self) -> &FrameStats {
+        &self.frame_stats
+    }
+
+    fn get_frame_stats_mut(&mut self) -> &mut FrameStats {
+        &mut self.frame_stats
+    }
+}
+
+/// Creates the window on `monitor`, the primary one, as `config` asks.
+fn create_window(
+    glfw: &mut Glfw,
+    monitor: Option<&glfw::Monitor>,
+    config: &WindowConfig,
+) -> Option<(Window, Receiver<(f64, WindowEvent)>)> {
+    let video_mode = monitor.and_then(|monitor| monitor.get_video_mode());
+    match (config.mode, monitor, video_mode) {
+        (WindowMode::Fullscreen, Some(monitor), _) => glfw.create_window(
+            config.width,
+            config.height,
+            &config.title,
+            glfw::WindowMode::FullScreen(monitor),
+        ),
+        (WindowMode::Borderless, Some(monitor), Some(video_mode)) => {
+            glfw.window_hint(glfw::WindowHint::Decorated(false));
+            let (x, y) = monitor.get_pos();
+            let created = glfw.create_window(
+                video_mode.width,
+                video_mode.height,
+                &config.title,
+                glfw::WindowMode::Windowed,
+            );
+            created.map(|(mut window, events)| {
+                window.set_pos(x, y);
+                (window, events)
+            })
+        }
+        _ => {
+            let created = glfw.create_window(
+                config.width,
+                config.height,
+                &config.title,
+                glfw::WindowMode::Windowed,
+            );
+            created.map(|(mut window, events)| {
+                match (config.position, monitor, video_mode) {
+                    (WindowPosition::At(x, y), _, _) => window.set_pos(x, y),
+                    (WindowPosition::Centered, Some(monitor), Some(video_mode)) => {
+                        let (x, y) = monitor.get_pos();
+                        let (width, height) = window.get_size();
+                        window.set_pos(
+                            x + (video_mode.width as i32 - width) / 2,
+                            y + (video_mode.height as i32 - height) / 2,
+                        );
+                    }
+                    _ => {}
+                }
+                (window, events)
+            })
+        }
+    }
+}
+
+/// The joysticks GLFW has a gamepad mapping for, from its SDL style database.
+struct JoystickGamepads {
+    glfw: Glfw,
+}
+
+impl GamepadSource for JoystickGamepads {
+    fn poll(&mut self) -> Vec<(GamepadId, GamepadReading)> {
+        (0..=glfw::ffi::JOYSTICK_LAST)
+            .filter_map(glfw::JoystickId::from_i32)
+            .map(|id| self.glfw.get_joystick(id))
+            .filter(|joystick| joystick.is_gamepad())
+            .filter_map(|joystick| {
+                let state = joystick.get_gamepad_state()?;
+                let buttons = GAMEPAD_BUTTONS
+                    .iter()
+                    .filter(|(button, _)| state.get_button_state(*button) == Action::Press)
+                    .map(|(_, button)| *button)
+                    .collect();
+                let axes = GAMEPAD_AXES
+                    .iter()
+                    .map(|(axis, ours)| {
+                        let value = state.get_axis(*axis);
+                        match ours {
+                            // GLFW triggers rest at -1
+                            GamepadAxis::LeftTrigger | GamepadAxis::RightTrigger => {
+                                (*ours, (value + 1.0) / 2.0)
+                            }
+                            _ => (*ours, value),
+                        }
+                    })
+                    .collect();
+                let reading = GamepadReading {
+                    name: joystick.get_gamepad_name().unwrap_or_default(),
+                    buttons,
+                    axes,
+                };
+                Some((GamepadId(joystick.id as u32), reading))
+            })
+            .collect()
+    }
+}
+
+const GAMEPAD_BUTTONS: [(glfw::GamepadButton, GamepadButton); 15] = [
+    (glfw::GamepadButton::ButtonA, GamepadButton::South),
+    (glfw::GamepadButton::ButtonB, GamepadButton::East),
+    (glfw::GamepadButton::ButtonX, GamepadButton::West),
+    (glfw::GamepadButton::ButtonY, GamepadButton::North),
+    (
+        glfw::GamepadButton::ButtonLeftBumper,
+        GamepadButton::LeftBumper,
+    ),
+    (
+        glfw::GamepadButton::ButtonRightBumper,
+        GamepadButton::RightBumper,
+    ),
+    (glfw::GamepadButton::ButtonBack, GamepadButton::Back),
+    (glfw::GamepadButton::ButtonStart, GamepadButton::Start),
+    (glfw::GamepadButton::ButtonGuide, GamepadButton::Guide),
+    (
+        glfw::GamepadButton::ButtonLeftThumb,
+        GamepadButton::LeftThumb,
+    ),
+    (
+        glfw::GamepadButton::ButtonRightThumb,
+        GamepadButton::RightThumb,
+    ),
+    (glfw::GamepadButton::ButtonDpadUp, GamepadButton::DPadUp),
+    (
+        glfw::GamepadButton::ButtonDpadRight,
+        GamepadButton::DPadRight,
+    ),
+    (glfw::GamepadButton::ButtonDpadDown, GamepadButton::DPadDown),
+    (glfw::GamepadButton::ButtonDpadLeft, GamepadButton::DPadLeft),
+];
+
+const GAMEPAD_AXES: [(glfw::GamepadAxis, GamepadAxis); 6] = [
+    (glfw::GamepadAxis::AxisLeftX, GamepadAxis::LeftX),
+    (glfw::GamepadAxis::AxisLeftY, GamepadAxis::LeftY),
+    (glfw::GamepadAxis::AxisRightX, GamepadAxis::RightX),
+    (glfw::GamepadAxis::AxisRightY, GamepadAxis::RightY),
+    (glfw::GamepadAxis::AxisLeftTrigger, GamepadAxis::LeftTrigger),
+    (
+        glfw::GamepadAxis::AxisRightTrigger,
+        GamepadAxis::RightTrigger,
+    ),
+];
+
+/// Moves `point` from the screen coordinates GLFW gives the cursor in to
+/// the framebuffer pixels the renderer works in, which are more of them on
+/// HiDPI screens.
+fn to_framebuffer(point: glm::Vec2, window: (i32, i32), framebuffer: (i32, i32)) -> glm::Vec2 {
+    if window.0 <= 0 || window.1 <= 0 {
+        return point;
+    }
+    let scale = glm::vec2(
+        framebuffer.0 as f32 / window.0 as f32,
+        framebuffer.1 as f32 / window.1 as f32,
+    );
+    point.component_mul(&scale)
+}
+
+fn translate_key(key: glfw::Key) -> Option<Key> {
+    use glfw::Key as G;
+    Some(match key {
+        G::A => Key::A,
+        G::B => Key::B,
+        G::C => Key::C,
+        G::D => Key::D,
+        G::E => Key::E,
+        G::F => Key::F,
+        G::G => Key::G,
+        G::H => Key::H,
+        G::I => Key::I,
+        G::J => Key::J,
+        G::K => Key::K,
+        G::L => Key::L,
+        G::M => Key::M,
+        G::N => Key::N,
+        G::O => Key::O,
+        G::P => Key::P,
+        G::Q => Key::Q,
+        G::R => Key::R,
+        G::S => Key::S,
+        G::T => Key::T,
+        G::U => Key::U,
+        G::V => Key::V,
+        G::W => Key::W,
+        G::X => Key::X,
+        G::Y => Key::Y,
+        G::Z => Key::Z,
+        G::Num0 => Key::Num0,
+        G::Num1 => Key::Num1,
+        G::Num2 => Key::Num2,
+        G::Num3 => Key::Num3,
+        G::Num4 => Key::Num4,
+        G::Num5 => Key::Num5,
+        G::Num6 => Key::Num6,
+        G::Num7 => Key::Num7,
+        G::Num8 => Key::Num8,
+        G::Num9 => Key::Num9,
+        G::F1 => Key::F1,
+        G::F2 => Key::F2,
+        G::F3 => Key::F3,
+        G::F4 => Key::F4,
+        G::F5 => Key::F5,
+        G::F6 => Key::F6,
+        G::F7 => Key::F7,
+        G::F8 => Key::F8,
+        G::F9 => Key::F9,
+        G::F10 => Key::F10,
+        G::F11 => Key::F11,
+        G::F12 => Key::F12,
+        G::Escape => Key::Escape,
+        G::Enter | G::KpEnter => Key::Enter,
+        G::Space => Key::Space,
+        G::Tab => Key::Tab,
+        G::Backspace => Key::Backspace,
+        G::Insert => Key::Insert,
+        G::Delete => Key::Delete,
+        G::Home => Key::Home,
+        G::End => Key::End,
+        G::PageUp => Key::PageUp,
+        G::PageDown => Key::PageDown,
+        G::Left => Key::Left,
+        G::Right => Key::Right,
+        G::Up => Key::Up,
+        G::Down => Key::Down,
+        G::LeftShift => Key::LShift,
+        G::RightShift => Key::RShift,
+        G::LeftControl => Key::LControl,
+        G::RightControl => Key::RControl,
+        G::LeftAlt => Key::LAlt,
+        G::RightAlt => Key::RAlt,
+        G::Minus => Key::Minus,
+        G::Equal => Key::Equals,
+        G::Comma => Key::Comma,
+        G::Period => Key::Period,
+        G::Slash => Key::Slash,
+        G::Semicolon => Key::Semicolon,
+        G::Apostrophe => Key::Apostrophe,
+        G::LeftBracket => Key::LBracket,
+        G::RightBracket => Key::RBracket,
+        G::Backslash => Key::Backslash,
+        G::GraveAccent => Key::Grave,
+        G::CapsLock => Key::CapsLock,
+        _ => return None,
+    })
+}
+
+fn translate_mouse_button(button: glfw::MouseButton) -> MouseButton {
+    match button {
+        glfw::MouseButton::Button1 => MouseButton::Left,
+        glfw::MouseButton::Button2 => MouseButton::Right,
+        glfw::MouseButton::Button3 => MouseButton::Middle,
+        other => MouseButton::Other(other as u16),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_cursor_is_scaled_to_framebuffer_pixels() {
+        let cursor = glm::vec2(100.0, 50.0);
+        assert_eq!(to_framebuffer(cursor, (800, 600), (800, 600)), cursor);
+        assert_eq!(
+            to_framebuffer(cursor, (800, 600), (1600, 1200)),
+            glm::vec2(200.0, 100.0)
+        );
+        assert_eq!(
+            to_framebuffer(cursor, (800, 600), (1200, 600)),
+            glm::vec2(150.0, 50.0)
+        );
+        // minimized
+        assert_eq!(to_framebuffer(cursor, (0, 0), (0, 0)), cursor);
     }
 }
@@ -0,0 +1,238 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use crate::error::Result;
+use crate::input::{GamepadSource, InputState, Key, MouseButton};
+use crate::panels::common::{GamePanel, Runnable};
+use crate::panels::frame_stats::FrameStats;
+use crate::panels::game_loop::GameLoop;
+use crate::panels::window_config::WindowConfig;
+use crate::renderer::backends::common::RenderBackend;
+use crate::renderer::backends::recording_backend::RecordingBackend;
+use crate::renderer::Renderer;
+use nalgebra_glm as glm;
+
+/// A panel without a window or GL context. It drives a `Runnable` for a fixed
+/// number of frames and feeds it scripted keys, mouse buttons, cursor and
+/// scroll per frame, so game logic can be exercised in tests and on CI. Each
+/// frame runs exactly one update.
+pub struct WindowHeadless {
+    pub width: u32,
+    pub height: u32,
+    renderer: Renderer,
+    input: InputState,
+    gamepads: Option<Box<dyn GamepadSource>>,
+    clipboard: Option<String>,
+    script: HashMap<usize, ScriptedInput>,
+    game_loop: GameLoop,
+    frame_stats: FrameStats,
+    frames: usize,
+    frame: usize,
+}
+
+impl WindowHeadless {
+    /// Replaces the default backend, a `RecordingBackend` keeping only the
+    /// last frame, e.g. with one keeping them all whose recording the test
+    /// kept a handle to.
+    pub fn with_backend(mut self, backend: Box<dyn RenderBackend>) -> Self {
+        let resolution = self.renderer.virtual_resolution();
+        self.renderer = Renderer::with_backend(self.width, self.height, backend);
+        self.renderer.set_virtual_resolution(resolution);
+        self
+    }
+
+    /// Polled before each frame, typically a `SimulatedGamepads` the test
+    /// kept a clone of. Without one no gamepad is ever connected.
+    pub fn with_gamepad_source(mut self, source: Box<dyn GamepadSource>) -> Self {
+        self.gamepads = Some(source);
+        self
+    }
+
+    /// How many frames `run` drives the runnable for.
+    pub fn with_frames(mut self, frames: usize) -> Self {
+        self.frames = frames;
+        self
+    }
+
+    /// Keys held in `get_input()` during `frame`. Frames without a script see no keys.
+    pub fn with_keys(mut self, frame: usize, keys: &[Key]) -> Self {
+        self.script
+            .entry(frame)
+            .or_default()
+            .keys
+            .extend(keys.iter().copied());
+        self
+    }
+
+    /// Text typed during `frame`, as if from the keyboard or an input method.
+    pub fn with_text(mut self, frame: usize, text: &str) -> Self {
+        self.script.entry(frame).or_default().text.push_str(text);
+        self
+    }
+
+    /// What the clipboard holds before the game copies anything, for
+    /// scripting Ctrl+V.
+    pub fn with_clipboard(mut self, text: &str) -> Self {
+        self.clipboard = Some(text.to_string());
+        self
+    }
+
+    /// Mouse buttons held during `frame`. Frames without a script see none.
+    pub fn with_mouse_buttons(mut self, frame: usize, buttons: &[MouseButton]) -> Self {
+        self.script
+            .entry(frame)
+            .or_default()
+            .mouse_buttons
+            .extend(buttons.iter().copied());
+        self
+    }
+
+    /// Moves the cursor, in window pixels, from `frame` on. `None` takes it
+    /// out of the window. The cursor starts outside.
+    pub fn with_cursor(mut self, frame: usize, position: Option<glm::Vec2>) -> Self {
+        self.script.entry(frame).or_default().cursor = Some(position);
+        self
+    }
+
+    /// Wheel movement seen by the update of `frame`, in lines.
+    pub fn with_scroll(mut self, frame: usize, delta: glm::Vec2) -> Self {
+        self.script.entry(frame).or_default().scroll += delta;
+        self
+    }
+
+    /// Holds `keys` down for every frame in `frames`.
+    pub fn hold_keys(mut self, frames: Range<usize>, keys: &[Key]) -> Self {
+        for frame in frames {
+            self = self.with_keys(frame, keys);
+        }
+        self
+    }
+
+    /// Index of the next frame to be run.
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    /// Runs a single frame, leaving the runnable with the caller for inspection.
+    pub fn step(&mut self, runnable: &mut impl Runnable) {
+        let script = self.script.remove(&self.frame).unwrap_or_default();
+        let released: Vec<Key> = self
+            .input
+            .pressed_keys()
+            .filter(|key| !script.keys.contains(key))
+            .collect();
+        for key in released {
+            self.input.release(key);
+        }
+        let pressed: Vec<Key> = script
+            .keys
+            .into_iter()
+            .filter(|key| !self.input.is_pressed(*key))
+            .collect();
+        for key in &pressed {
+            self.input.press(*key);
+        }
+        // after pressing them all, so modifiers scripted on the same frame count
+        for key in pressed {
+            self.input.type_key(key, || self.clipboard.clone());
+        }
+        self.input.insert_text(&script.text);
+        let released: Vec<MouseButton> = self
+            .input
+            .pressed_mouse_buttons()
+            .filter(|button| !script.mouse_buttons.contains(button))
+            .collect();
+        for button in released {
+            self.input.release_mouse(button);
+        }
+        for button in script.mouse_buttons {
+            self.input.press_mouse(button);
+        }
+        if let Some(cursor) = script.cursor {
+            self.input.set_cursor(cursor);
+        }
+        self.input.add_scroll(script.scroll);
+        if let Some(source) = &mut self.gamepads {
+            self.input.update_gamepads(source.poll());
+        }
+
+        let mut game_loop = std::mem::take(&mut self.game_loop);
+        let timestep = game_loop.timestep();
+        game_loop.frame(self, runnable, timestep);
+        self.game_loop = game_loop;
+
+        self.frame += 1;
+    }
+
+    pub fn run_frames(&mut self, runnable: &mut impl Runnable, frames: usize) {
+        for _ in 0..frames {
+            self.step(runnable);
+        }
+    }
+}
+
+impl GamePanel for WindowHeadless {
+    fn try_build_with(config: WindowConfig) -> Result<Self> {
+        let (width, height) = config.size();
+        let mut renderer =
+            Renderer::try_with_backend(width, height, Box::new(RecordingBackend::last_frame()))?;
+        renderer.set_virtual_resolution(config.virtual_resolution);
+        Ok(Self {
+            width,
+            height,
+            renderer,
+            input: InputState::default(),
+            gamepads: None,
+            clipboard: None,
+            script: HashMap::new(),
+            game_loop: GameLoop::new(config.update_rate),
+            frame_stats: FrameStats::default(),
+            frames: 1,
+            frame: 0,
+        })
+    }
+
+    fn run(mut self, mut runnable: impl Runnable + 'static) {
+        let frames = self.frames;
+        self.run_frames(&mut runnable, frames);
+    }
+
+    fn get_renderer(&mut self) -> &mut Renderer {
+        &mut self.renderer
+    }
+
+    fn get_input(&self) -> &InputState {
+        &self.input
+    }
+
+    fn get_input_mut(&mut self) -> &mut InputState {
+        &mut self.input
+    }
+
+    fn get_clipboard(&mut self) -> Option<String> {
+        self.clipboard.clone()
+    }
+
+    fn set_clipboard(&mut self, text: &str) {
+        self.clipboard = Some(text.to_string());
+    }
+
+    fn get_frame_stats(&self) -> &FrameStats {
+        &self.frame_stats
+    }
+
+    fn get_frame_stats_mut(&mut self) -> &mut FrameStats {
+        &mut self.frame_stats
+    }
+}
+
+/// Input scripted for one frame of a `WindowHeadless`.
+#[derive(Default)]
+struct ScriptedInput {
+    keys: HashSet<Key>,
+    mouse_buttons: HashSet<MouseButton>,
+    text: String,
+    /// `Some` when the cursor moves on this frame.
+    cursor: Option<Option<glm::Vec2>>,
+    scroll: glm::Vec2,
+}
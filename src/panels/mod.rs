@@ -1,3 +1,9 @@
 pub mod common;
-// pub mod glfw_panel;
+pub mod frame_stats;
+pub mod game_loop;
+#[cfg(feature = "gilrs")]
+mod gilrs_gamepads;
+pub mod glfw_panel;
+pub mod headless_panel;
+pub mod window_config;
 pub mod winit_panel;
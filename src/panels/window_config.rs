@@ -0,0 +1,195 @@
+use image::RgbaImage;
+use std::time::Duration;
+
+use crate::error::Result;
+use crate::panels::game_loop::{interval, DEFAULT_UPDATE_RATE};
+use crate::renderer::texture::load_image_from_file;
+use crate::renderer::viewport::{Scaling, VirtualResolution};
+
+/// How the window covers the screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowMode {
+    Windowed,
+    /// Exclusive fullscreen on the primary monitor, switching it to the video
+    /// mode closest to the configured size.
+    Fullscreen,
+    /// A window without decorations covering the primary monitor, at its
+    /// current video mode.
+    Borderless,
+}
+
+/// Where a windowed window opens.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowPosition {
+    /// Centered on the primary monitor.
+    Centered,
+    /// Top left corner of the window, in screen pixels.
+    At(i32, i32),
+}
+
+/// Window, GL context and game loop settings, taken by
+/// `GamePanel::try_build_with`. Panels without a window only use the size,
+/// the virtual resolution and the update rate.
+#[derive(Clone, Debug)]
+pub struct WindowConfig {
+    pub(crate) title: String,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) resizable: bool,
+    pub(crate) vsync: bool,
+    pub(crate) mode: WindowMode,
+    pub(crate) icon: Option<String>,
+    pub(crate) samples: Option<u16>,
+    pub(crate) gl_version: (u8, u8),
+    pub(crate) position: WindowPosition,
+    pub(crate) virtual_resolution: Option<VirtualResolution>,
+    pub(crate) update_rate: f64,
+    pub(crate) max_frame_rate: Option<f64>,
+}
+
+impl WindowConfig {
+    /// A fixed size, centered window titled "Omak", without vsync or MSAA,
+    /// on a GL 3.3 core context, updated `DEFAULT_UPDATE_RATE` times per second.
+    pub fn init(width: u32, height: u32) -> Self {
+        Self {
+            title: "Omak".to_string(),
+            width,
+            height,
+            resizable: false,
+            vsync: false,
+            mode: WindowMode::Windowed,
+            icon: None,
+            samples: None,
+            gl_version: (3, 3),
+            position: WindowPosition::Centered,
+            virtual_resolution: None,
+            update_rate: DEFAULT_UPDATE_RATE,
+            max_frame_rate: None,
+        }
+    }
+
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    pub fn with_resizable(mut self, resizable: bool) -> Self {
+        self.resizable = resizable;
+        self
+    }
+
+    /// Waits for the monitor's refresh before presenting each frame.
+    pub fn with_vsync(mut self, vsync: bool) -> Self {
+        self.vsync = vsync;
+        self
+    }
+
+    pub fn with_mode(mut self, mode: WindowMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Image shown in the title bar and task bar, loaded when the window is built.
+    pub fn with_icon(mut self, img_path: &str) -> Self {
+        self.icon = Some(img_path.to_string());
+        self
+    }
+
+    /// Samples per pixel for multisample antialiasing.
+    pub fn with_samples(mut self, samples: u16) -> Self {
+        self.samples = Some(samples);
+        self
+    }
+
+    /// Core profile version of the GL context, 3.3 at least for the renderer's shaders.
+    pub fn with_gl_version(mut self, major: u8, minor: u8) -> Self {
+        self.gl_version = (major, minor);
+        self
+    }
+
+    pub fn with_position(mut self, position: WindowPosition) -> Self {
+        self.position = position;
+        self
+    }
+
+    /// Draws at `width` x `height` whatever the window size, see
+    /// `Renderer::set_virtual_resolution`. Usually paired with `with_resizable`.
+    pub fn with_virtual_resolution(mut self, width: u32, height: u32, scaling: Scaling) -> Self {
+        self.virtual_resolution = Some(VirtualResolution::new(width, height, scaling));
+        self
+    }
+
+    /// Updates per second, the rate `Runnable::update` is called at whatever
+    /// the frame rate. Rates that are not positive are ignored with a warning.
+    pub fn with_update_rate(mut self, update_rate: f64) -> Self {
+        match interval(update_rate) {
+            Some(_) => self.update_rate = update_rate,
+            None => log::warn!("Ignoring an update rate of {}", update_rate),
+        }
+        self
+    }
+
+    /// Frames per second rendering is held to. Without it, frames are paced
+    /// by vsync when it is on, and by the update rate otherwise, so the panel
+    /// sleeps between frames rather than spinning. Rates that are not
+    /// positive are ignored with a warning.
+    pub fn with_max_frame_rate(mut self, max_frame_rate: f64) -> Self {
+        match interval(max_frame_rate) {
+            Some(_) => self.max_frame_rate = Some(max_frame_rate),
+            None => log::warn!("Ignoring a max frame rate of {}", max_frame_rate),
+        }
+        self
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Shortest time between two frames, `None` when vsync paces them.
+    pub(crate) fn frame_interval(&self) -> Option<Duration> {
+        match self.max_frame_rate {
+            Some(rate) => interval(rate),
+            None if self.vsync => None,
+            None => interval(self.update_rate),
+        }
+    }
+
+    pub(crate) fn load_icon(&self) -> Result<Option<RgbaImage>> {
+        self.icon
+            .as_deref()
+            .map(|path| load_image_from_file(path).map(|image| image.to_rgba8()))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_are_paced_by_vsync_or_the_update_rate() {
+        let config = WindowConfig::init(320, 240).with_update_rate(50.0);
+        assert_eq!(config.frame_interval(), Some(Duration::from_millis(20)));
+        let config = config.with_vsync(true);
+        assert_eq!(config.frame_interval(), None);
+        let config = config.with_max_frame_rate(100.0);
+        assert_eq!(config.frame_interval(), Some(Duration::from_millis(10)));
+    }
+
+    #[test]
+    fn rates_that_are_not_positive_are_ignored() {
+        for rate in [0.0, -30.0, f64::NAN, f64::INFINITY, 1e300] {
+            let config = WindowConfig::init(320, 240)
+                .with_update_rate(50.0)
+                .with_update_rate(rate)
+                .with_max_frame_rate(rate);
+            assert_eq!(config.update_rate, 50.0, "{}", rate);
+            assert_eq!(config.max_frame_rate, None, "{}", rate);
+            assert_eq!(config.frame_interval(), Some(Duration::from_millis(20)));
+        }
+    }
+}
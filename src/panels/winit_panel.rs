@@ -1,62 +1,95 @@
+use crate::error::{Error, Result};
+use crate::input::{GamepadSource, InputState, Key, MouseButton};
 use crate::panels::common::{GamePanel, Runnable};
+use crate::panels::frame_stats::FrameStats;
+use crate::panels::game_loop::GameLoop;
+#[cfg(feature = "gilrs")]
+use crate::panels::gilrs_gamepads::GilrsGamepads;
+use crate::panels::window_config::{WindowConfig, WindowMode, WindowPosition};
 use crate::renderer::Renderer;
-use gl::types::*;
 use glutin::dpi::PhysicalPosition;
-use std::collections::HashSet;
-use std::time::{Duration, Instant, SystemTime};
+use glutin::{Api, GlProfile, GlRequest};
+use nalgebra_glm as glm;
+use std::time::Instant;
 use winit::{
     dpi,
-    event::{ElementState, Event, VirtualKeyCode as Key, WindowEvent},
+    event::{ElementState, Event, Ime, MouseScrollDelta, VirtualKeyCode, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
-    window::{Window, WindowBuilder},
+    monitor::{MonitorHandle, VideoMode},
+    window::{Fullscreen, Icon, Window, WindowBuilder},
 };
 
-const FPS: f64 = 60.0;
+/// Touchpads scroll in pixels, wheels in lines.
+const PIXELS_PER_LINE: f32 = 120.0;
 
 pub struct WindowWinit {
     event_loop: Option<EventLoop<()>>,
-    ctx: glutin::ContextWrapper<glutin::PossiblyCurrent, Window>,
+    // dropped before the context, which its GL objects belong to
     renderer: Renderer,
-    keys: HashSet<Key>,
-    time_created: Instant,
+    ctx: glutin::ContextWrapper<glutin::PossiblyCurrent, Window>,
+    input: InputState,
+    gamepads: Option<Box<dyn GamepadSource>>,
+    clipboard: Clipboard,
+    game_loop: GameLoop,
+    frame_stats: FrameStats,
 }
 
 impl WindowWinit {
+    /// Replaces the gamepads read through gilrs, if the `gilrs` feature is
+    /// on, e.g. with a `SimulatedGamepads`.
+    pub fn with_gamepad_source(mut self, source: Box<dyn GamepadSource>) -> Self {
+        self.gamepads = Some(source);
+        self
+    }
+
     fn user_input(&mut self, event: &Event<()>) {
         match event {
             Event::WindowEvent { ref event, .. } => match event {
                 WindowEvent::KeyboardInput { input, .. } => {
-                    if let Some(keycode) = input.virtual_keycode {
+                    if let Some(key) = input.virtual_keycode.and_then(translate_key) {
                         match input.state {
                             ElementState::Pressed => {
-                                // self.keys.clear();
-                                self.keys.insert(keycode);                           }
-                            ElementState::Released => {
-                                self.keys.remove(&keycode);                           }
+                                self.input.press(key);
+                                self.input.type_key(key, || self.clipboard.get());
+                            }
+                            ElementState::Released => self.input.release(key),
                         }
                     }
                 }
-                WindowEvent::MouseWheel { delta, .. } => match delta {
-                    winit::event::MouseScrollDelta::LineDelta(x, y) => {
-                        println!("mouse wheel Line Delta: ({},{})", x, y);
-                        let pixels_per_line = 120.0;
-                        let mut pos = self.ctx.window().outer_position().unwrap();
-                        pos.x += (x * pixels_per_line) as i32;
-                        pos.y += (y * pixels_per_line) as i32;
-                        self.ctx.window().set_outer_position(pos)
-                    }
-                    winit::event::MouseScrollDelta::PixelDelta(p) => {
-                        println!("mouse wheel Pixel Delta: ({},{})", p.x, p.y);
-                        let mut pos = self.ctx.window().outer_position().unwrap();
-                        pos.x += p.x as i32;
-                        pos.y += p.y as i32;
-                        self.ctx.window().set_outer_position(pos)
+                WindowEvent::ReceivedCharacter(c) => {
+                    self.input.insert_text(c.encode_utf8(&mut [0; 4]))
+                }
+                WindowEvent::Ime(Ime::Preedit(text, _)) => {
+                    self.input.set_composition(Some(text.clone()))
+                }
+                WindowEvent::Ime(Ime::Commit(text)) => {
+                    self.input.set_composition(None);
+                    self.input.insert_text(text);
+                }
+                WindowEvent::Ime(Ime::Disabled) => self.input.set_composition(None),
+                WindowEvent::MouseInput { state, button, .. } => {
+                    let button = translate_mouse_button(*button);
+                    match state {
+                        ElementState::Pressed => self.input.press_mouse(button),
+                        ElementState::Released => self.input.release_mouse(button),
                     }
+                }
+                WindowEvent::Focused(false) => self.input.release_all(),
+                WindowEvent::CursorMoved { position, .. } => {
+                    self.input
+                        .set_cursor(Some(glm::vec2(position.x as f32, position.y as f32)));
+                }
+                WindowEvent::CursorLeft { .. } => self.input.set_cursor(None),
+                WindowEvent::MouseWheel { delta, .. } => match delta {
+                    MouseScrollDelta::LineDelta(x, y) => self.input.add_scroll(glm::vec2(*x, *y)),
+                    MouseScrollDelta::PixelDelta(p) => self.input.add_scroll(
+                        glm::vec2(p.x as f32, p.y as f32) / PIXELS_PER_LINE,
+                    ),
                 },
 
                 _ => {}
             },
-                
+
             Event::DeviceEvent {
                 // event: DeviceEvent::MouseMotion { delta: (dx, dy) },
                 ..
@@ -70,104 +103,137 @@ impl WindowWinit {
 }
 
 impl GamePanel for WindowWinit {
-    fn build(width: u32, height: u32) -> Self {
-        let window_builder = WindowBuilder::new()
-            .with_title("Omak")
-            .with_inner_size(dpi::PhysicalSize::new(width, height))
-            .with_resizable(false)
-            .with_position(PhysicalPosition::new(550, 250));
+    fn try_build_with(config: WindowConfig) -> Result<Self> {
         let event_loop = EventLoop::new();
+        let monitor = event_loop.primary_monitor();
+        let fullscreen = match config.mode {
+            WindowMode::Windowed => None,
+            WindowMode::Fullscreen => monitor
+                .as_ref()
+                .and_then(|monitor| closest_video_mode(monitor, config.width, config.height))
+                .map(Fullscreen::Exclusive),
+            WindowMode::Borderless => Some(Fullscreen::Borderless(monitor.clone())),
+        };
+        let icon = match config.load_icon()? {
+            Some(image) => {
+                let (width, height) = image.dimensions();
+                Some(
+                    Icon::from_rgba(image.into_raw(), width, height)
+                        .map_err(|e| Error::Context(e.to_string()))?,
+                )
+            }
+            None => None,
+        };
+        let mut window_builder = WindowBuilder::new()
+            .with_title(&config.title)
+            .with_inner_size(dpi::PhysicalSize::new(config.width, config.height))
+            .with_resizable(config.resizable)
+            .with_fullscreen(fullscreen)
+            .with_window_icon(icon);
+        if let WindowPosition::At(x, y) = config.position {
+            window_builder = window_builder.with_position(PhysicalPosition::new(x, y));
+        }
+        let (major, minor) = config.gl_version;
+        let mut ctx_builder = glutin::ContextBuilder::new()
+            .with_gl(GlRequest::Specific(Api::OpenGl, (major, minor)))
+            .with_gl_profile(GlProfile::Core)
+            .with_vsync(config.vsync);
+        if let Some(samples) = config.samples {
+            ctx_builder = ctx_builder.with_multisampling(samples);
+        }
         unsafe {
-            let ctx = glutin::ContextBuilder::new()
-                .with_vsync(false)
+            let ctx = ctx_builder
                 .build_windowed(window_builder, &event_loop)
-                .unwrap();
-            let ctx = ctx.make_current().unwrap();
+                .map_err(|e| Error::Context(e.to_string()))?;
+            let ctx = ctx
+                .make_current()
+                .map_err(|(_, e)| Error::Context(e.to_string()))?;
             gl::load_with(|symbol| ctx.get_proc_address(symbol) as *const _);
             let window_size = ctx.window().inner_size();
-            let monitor_size = ctx.window().current_monitor().unwrap().size();
 
-            // center the window
-            ctx.window().set_outer_position(PhysicalPosition::new(
-                (monitor_size.width - window_size.width) / 2,
-                (monitor_size.height - window_size.height) / 2,
-            ));
+            if let (WindowMode::Windowed, WindowPosition::Centered, Some(monitor)) =
+                (config.mode, config.position, &monitor)
+            {
+                let monitor_size = monitor.size();
+                let origin = monitor.position();
+                ctx.window().set_outer_position(PhysicalPosition::new(
+                    origin.x + (monitor_size.width.saturating_sub(window_size.width) / 2) as i32,
+                    origin.y + (monitor_size.height.saturating_sub(window_size.height) / 2) as i32,
+                ));
+            }
 
-            gl::Viewport(0, 0, window_size.width as i32, window_size.height as i32);
-            Self {
+            let mut renderer = Renderer::try_new(window_size.width, window_size.height)?;
+            renderer.set_virtual_resolution(config.virtual_resolution);
+            Ok(Self {
                 ctx,
                 event_loop: Some(event_loop),
-                renderer: Renderer::new(window_size.width, window_size.height),
-                keys: HashSet::new(),
-                time_created: Instant::now(),
-            }
+                renderer,
+                input: InputState::default(),
+                gamepads: default_gamepads(),
+                clipboard: Clipboard::new(),
+                game_loop: GameLoop::from_config(&config),
+                frame_stats: FrameStats::default(),
+            })
         }
     }
 
-    fn run(mut self, mut runnable: impl Runnable + 'static) {
-        let event_loop = self.event_loop.unwrap();
-        self.event_loop = None;
+    fn run(mut self, runnable: impl Runnable + 'static) {
+        let event_loop = self.event_loop.take().unwrap();
 
-        let time_per_frame = 1000000000.0 / FPS;
-        let mut prev_time = self.time_created.elapsed().as_nanos();
-        let mut frame_count = 0;
-        let mut last_check = self.time_created.elapsed().as_nanos();
-        let mut delta = 0.0;
+        let mut game_loop = std::mem::take(&mut self.game_loop);
 
+        // `run` never returns, so the game is dropped by hand on exit
+        let mut running = Some((self, runnable));
         event_loop.run(move |event, _, control_flow| {
-            *control_flow = ControlFlow::Poll;
-            match event {
-                Event::LoopDestroyed => {
-                    return;
+            if let Event::LoopDestroyed = event {
+                if let Some((panel, runnable)) = running.take() {
+                    drop(runnable);
+                    drop(panel);
                 }
-
+                crate::renderer::report_live_objects();
+                return;
+            }
+            let (panel, runnable) = match running.as_mut() {
+                Some((panel, runnable)) => (panel, runnable),
+                None => return,
+            };
+            match event {
                 // "Emitted when all of the event loop's input events have been processed
                 // and redraw processing is about to begin"
-                Event::MainEventsCleared => {
-                    self.ctx.window().request_redraw();
-                }
+                Event::MainEventsCleared => match game_loop.next_frame() {
+                    // sleeps until the frame is due, woken early by input
+                    Some(at) if at > Instant::now() => {
+                        *control_flow = ControlFlow::WaitUntil(at);
+                    }
+                    _ => {
+                        *control_flow = ControlFlow::Poll;
+                        panel.ctx.window().request_redraw();
+                    }
+                },
 
                 // Draw to the screen when requested
                 Event::RedrawRequested(_) => {
-                    let current_time = self.time_created.elapsed().as_nanos();
-
-                    delta += (current_time - prev_time) as f64 / time_per_frame;
-                    prev_time = current_time;
-
-                    if delta >= 1.0 {
-                        self.renderer.clear();
-                        runnable.run(&mut self);
-                        self.ctx.swap_buffers().unwrap();
-                        delta -= 1.0;
-
-                        //should be deleted when textures loading delay will be fixed
-                        // if delta > 2.0 {
-                        //     delta = 0.0;
-                        // }
-                        //----------
-
-                        frame_count += 1;
-                    }
-
-                    if current_time - last_check >= 1000000000 {
-                        last_check = current_time;
-                        // println!("FPS: {}", frame_count);
-                        frame_count = 0;
+                    if let Some(source) = &mut panel.gamepads {
+                        panel.input.update_gamepads(source.poll());
                     }
+                    let elapsed = game_loop.elapsed();
+                    game_loop.frame(panel, runnable, elapsed);
+                    panel.ctx.swap_buffers().unwrap();
                 }
 
                 Event::WindowEvent { ref event, .. } => match event {
-                    // Resize OpenGL viewport when window is resized
-                    WindowEvent::Resized(size) => unsafe {
-                        gl::Viewport(0, 0, size.width as i32, size.height as i32);
-                    },
+                    // Rescale the frame when window is resized
+                    WindowEvent::Resized(size) => {
+                        panel.ctx.resize(*size);
+                        panel.renderer.resize(size.width, size.height);
+                    }
 
                     // Exit loop when CloseRequested raised
                     WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
 
                     WindowEvent::KeyboardInput { input, .. } => match input.virtual_keycode {
                         // Exit loop when Escape is pressed
-                        Some(Key::Escape) => {
+                        Some(VirtualKeyCode::Escape) => {
                             *control_flow = ControlFlow::Exit;
                         }
                         _ => {}
@@ -176,7 +242,7 @@ impl GamePanel for WindowWinit {
                 },
                 _ => (),
             }
-            self.user_input(&event);
+            panel.user_input(&event);
         });
     }
 
@@ -184,7 +250,186 @@ impl GamePanel for WindowWinit {
         &mut self.renderer
     }
 
-    fn get_keys(&self) -> &HashSet<Key> {
-        &self.keys
+    fn get_input(&self) -> &InputState {
+        &self.input
+    }
+
+    fn get_input_mut(&mut self) -> &mut InputState {
+        &mut self.input
+    }
+
+    fn get_clipboard(&mut self) -> Option<String> {
+        self.clipboard.get()
+    }
+
+    fn set_clipboard(&mut self, text: &str) {
+        self.clipboard.set(text);
+    }
+
+    fn set_text_input(&mut self, enabled: bool) {
+        self.ctx.window().set_ime_allowed(enabled);
+        if !enabled {
+            self.input.set_composition(None);
+        }
+    }
+
+    fn get_frame_stats(&self) -> &FrameStats {
+        &self.frame_stats
+    }
+
+    fn get_frame_stats_mut(&mut self) -> &mut FrameStats {
+        &mut self.frame_stats
+    }
+}
+
+/// The monitor's video mode nearest to `width` x `height`, highest refresh
+/// rate first among equals.
+fn closest_video_mode(monitor: &MonitorHandle, width: u32, height: u32) -> Option<VideoMode> {
+    monitor.video_modes().min_by_key(|mode| {
+        let size = mode.size();
+        (
+            size.width.abs_diff(width) + size.height.abs_diff(height),
+            std::cmp::Reverse(mode.refresh_rate_millihertz()),
+        )
+    })
+}
+
+/// The system clipboard through arboard, as winit has no clipboard access.
+/// Where arboard cannot reach it, pasting only sees what the game copied.
+struct Clipboard {
+    system: Option<arboard::Clipboard>,
+    own: Option<String>,
+}
+
+impl Clipboard {
+    fn new() -> Self {
+        let system = arboard::Clipboard::new()
+            .map_err(|e| log::warn!("The system clipboard is unavailable: {}", e))
+            .ok();
+        Self { system, own: None }
+    }
+
+    fn get(&mut self) -> Option<String> {
+        match &mut self.system {
+            Some(system) => system.get_text().ok(),
+            None => self.own.clone(),
+        }
+    }
+
+    fn set(&mut self, text: &str) {
+        match &mut self.system {
+            Some(system) => {
+                if let Err(e) = system.set_text(text) {
+                    log::warn!("Could not copy to the clipboard: {}", e);
+                }
+            }
+            None => self.own = Some(text.to_string()),
+        }
+    }
+}
+
+/// Winit has no gamepad support: they are read through gilrs with the
+/// `gilrs` feature, and only come from `with_gamepad_source` without it.
+fn default_gamepads() -> Option<Box<dyn GamepadSource>> {
+    #[cfg(feature = "gilrs")]
+    return GilrsGamepads::try_new().map(|pads| Box::new(pads) as _);
+    #[cfg(not(feature = "gilrs"))]
+    None
+}
+
+fn translate_key(key: VirtualKeyCode) -> Option<Key> {
+    use VirtualKeyCode as V;
+    Some(match key {
+        V::A => Key::A,
+        V::B => Key::B,
+        V::C => Key::C,
+        V::D => Key::D,
+        V::E => Key::E,
+        V::F => Key::F,
+        V::G => Key::G,
+        V::H => Key::H,
+        V::I => Key::I,
+        V::J => Key::J,
+        V::K => Key::K,
+        V::L => Key::L,
+        V::M => Key::M,
+        V::N => Key::N,
+        V::O => Key::O,
+        V::P => Key::P,
+        V::Q => Key::Q,
+        V::R => Key::R,
+        V::S => Key::S,
+        V::T => Key::T,
+        V::U => Key::U,
+        V::V => Key::V,
+        V::W => Key::W,
+        V::X => Key::X,
+        V::Y => Key::Y,
+        V::Z => Key::Z,
+        V::Key0 => Key::Num0,
+        V::Key1 => Key::Num1,
+        V::Key2 => Key::Num2,
+        V::Key3 => Key::Num3,
+        V::Key4 => Key::Num4,
+        V::Key5 => Key::Num5,
+        V::Key6 => Key::Num6,
+        V::Key7 => Key::Num7,
+        V::Key8 => Key::Num8,
+        V::Key9 => Key::Num9,
+        V::F1 => Key::F1,
+        V::F2 => Key::F2,
+        V::F3 => Key::F3,
+        V::F4 => Key::F4,
+        V::F5 => Key::F5,
+        V::F6 => Key::F6,
+        V::F7 => Key::F7,
+        V::F8 => Key::F8,
+        V::F9 => Key::F9,
+        V::F10 => Key::F10,
+        V::F11 => Key::F11,
+        V::F12 => Key::F12,
+        V::Escape => Key::Escape,
+        V::Return | V::NumpadEnter => Key::Enter,
+        V::Space => Key::Space,
+        V::Tab => Key::Tab,
+        V::Back => Key::Backspace,
+        V::Insert => Key::Insert,
+        V::Delete => Key::Delete,
+        V::Home => Key::Home,
+        V::End => Key::End,
+        V::PageUp => Key::PageUp,
+        V::PageDown => Key::PageDown,
+        V::Left => Key::Left,
+        V::Right => Key::Right,
+        V::Up => Key::Up,
+        V::Down => Key::Down,
+        V::LShift => Key::LShift,
+        V::RShift => Key::RShift,
+        V::LControl => Key::LControl,
+        V::RControl => Key::RControl,
+        V::LAlt => Key::LAlt,
+        V::RAlt => Key::RAlt,
+        V::Minus => Key::Minus,
+        V::Equals => Key::Equals,
+        V::Comma => Key::Comma,
+        V::Period => Key::Period,
+        V::Slash => Key::Slash,
+        V::Semicolon => Key::Semicolon,
+        V::Apostrophe => Key::Apostrophe,
+        V::LBracket => Key::LBracket,
+        V::RBracket => Key::RBracket,
+        V::Backslash => Key::Backslash,
+        V::Grave => Key::Grave,
+        V::Capital => Key::CapsLock,
+        _ => return None,
+    })
+}
+
+fn translate_mouse_button(button: winit::event::MouseButton) -> MouseButton {
+    match button {
+        winit::event::MouseButton::Left => MouseButton::Left,
+        winit::event::MouseButton::Right => MouseButton::Right,
+        winit::event::MouseButton::Middle => MouseButton::Middle,
+        winit::event::MouseButton::Other(n) => MouseButton::Other(n),
     }
 }
@@ -0,0 +1,77 @@
+use std::fmt;
+use std::ops::Deref;
+use std::rc::{Rc, Weak};
+
+struct Loaded<T> {
+    path: String,
+    asset: T,
+}
+
+impl<T> Drop for Loaded<T> {
+    fn drop(&mut self) {
+        // the asset frees its GL objects itself, right after
+        log::debug!("Releasing {}", self.path);
+    }
+}
+
+/// Reference-counted handle to an asset loaded by `ResourcesManager`.
+/// Loading the same path again returns the same asset, which is dropped
+/// along with its GL objects when the last handle is. Handles stay on the
+/// thread of the GL context.
+pub struct Handle<T> {
+    inner: Rc<Loaded<T>>,
+}
+impl<T> Handle<T> {
+    pub(crate) fn new(path: &str, asset: T) -> Self {
+        Self {
+            inner: Rc::new(Loaded {
+                path: path.to_string(),
+                asset,
+            }),
+        }
+    }
+
+    /// Path (or key, for sprite sheets) the asset was loaded from.
+    pub fn path(&self) -> &str {
+        &self.inner.path
+    }
+
+    /// True when both handles point to the same loaded asset.
+    pub fn ptr_eq(a: &Self, b: &Self) -> bool {
+        Rc::ptr_eq(&a.inner, &b.inner)
+    }
+
+    pub(crate) fn downgrade(&self) -> WeakHandle<T> {
+        WeakHandle(Rc::downgrade(&self.inner))
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Rc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T> Deref for Handle<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner.asset
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Handle").field(&self.inner.path).finish()
+    }
+}
+
+/// Cache entry of the `ResourcesManager`, which must not keep assets alive.
+pub(crate) struct WeakHandle<T>(Weak<Loaded<T>>);
+impl<T> WeakHandle<T> {
+    pub(crate) fn upgrade(&self) -> Option<Handle<T>> {
+        self.0.upgrade().map(|inner| Handle { inner })
+    }
+}
@@ -0,0 +1,157 @@
+use crate::error::Result;
+use crate::renderer::texture::{load_image_from_file, Texture, TextureStorage};
+use crate::renderer::ImgKind;
+use image::{DynamicImage, RgbaImage};
+use std::collections::HashMap;
+
+/// Several loose images packed into a single texture, looked up by name.
+pub struct Atlas {
+    pub texture: Texture,
+    regions: HashMap<String, Texture>,
+}
+impl Atlas {
+    pub fn get(&self, name: &str) -> Option<Texture> {
+        self.regions.get(name).cloned()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.regions.keys().map(String::as_str)
+    }
+}
+
+/// Packs images into rows ("shelves") no wider than `max_width`, tallest first.
+pub struct AtlasBuilder {
+    max_width: u32,
+    padding: u32,
+    images: Vec<(String, RgbaImage)>,
+    storage: TextureStorage,
+}
+impl AtlasBuilder {
+    pub fn init(max_width: u32) -> Self {
+        Self {
+            max_width,
+            padding: 1,
+            images: Vec::new(),
+            storage: TextureStorage::Gpu,
+        }
+    }
+
+    /// Transparent pixels left around every image, 1 by default.
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn with_storage(mut self, storage: TextureStorage) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    pub fn with_image(self, name: &str, img_path: &str) -> Self {
+        self.try_with_image(name, img_path)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_with_image(self, name: &str, img_path: &str) -> Result<Self> {
+        Ok(self.with_pixels(name, load_image_from_file(img_path)?))
+    }
+
+    pub fn with_pixels(mut self, name: &str, image: DynamicImage) -> Self {
+        self.images.push((name.to_string(), image.to_rgba8()));
+        self
+    }
+
+    pub fn build(mut self) -> Atlas {
+        self.images
+            .sort_by(|(_, a), (_, b)| b.height().cmp(&a.height()).then(b.width().cmp(&a.width())));
+
+        // place every image on a shelf, starting a new one when the row is full
+        let mut placements = Vec::with_capacity(self.images.len());
+        let (mut x, mut y, mut shelf_height, mut width) = (0, 0, 0, 0);
+        for (name, image) in &self.images {
+            let (w, h) = (image.width() + self.padding, image.height() + self.padding);
+            // the shelf ends with the padding right of its last image
+            if x > 0 && x + w + self.padding > self.max_width {
+                y += shelf_height;
+                x = 0;
+                shelf_height = 0;
+            }
+            placements.push((name, x + self.padding, y + self.padding));
+            x += w;
+            shelf_height = shelf_height.max(h);
+            width = width.max(x + self.padding);
+        }
+        let height = y + shelf_height + self.padding;
+
+        let mut pixels = RgbaImage::new(width.max(1), height.max(1));
+        for ((_, image), (_, x, y)) in self.images.iter().zip(&placements) {
+            image::imageops::replace(&mut pixels, image, *x as i64, *y as i64);
+        }
+        let texture = Texture::new(DynamicImage::ImageRgba8(pixels), ImgKind::PNG, self.storage);
+        log::debug!(
+            "Packed {} images into a {}x{} atlas.",
+            placements.len(),
+            width,
+            height
+        );
+
+        let regions = self
+            .images
+            .iter()
+            .zip(placements)
+            .map(|((_, image), (name, x, y))| {
+                (
+                    name.clone(),
+                    texture.sub_texture(x, y, image.width(), image.height()),
+                )
+            })
+            .collect();
+        Atlas { texture, regions }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(size: u32) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::new(size, size))
+    }
+
+    #[test]
+    fn shelves_stay_within_max_width_padding_included() {
+        // 1 + 4 + 1 + 4 + 1 fits 11 pixels exactly, a third image wraps
+        let atlas = AtlasBuilder::init(11)
+            .with_storage(TextureStorage::Cpu)
+            .with_pixels("a", square(4))
+            .with_pixels("b", square(4))
+            .with_pixels("c", square(4))
+            .build();
+        assert_eq!((atlas.texture.width, atlas.texture.height), (11, 11));
+
+        let atlas = AtlasBuilder::init(10)
+            .with_storage(TextureStorage::Cpu)
+            .with_pixels("a", square(4))
+            .with_pixels("b", square(4))
+            .build();
+        assert_eq!((atlas.texture.width, atlas.texture.height), (6, 11));
+    }
+
+    #[test]
+    fn regions_point_at_their_images() {
+        let atlas = AtlasBuilder::init(64)
+            .with_storage(TextureStorage::Cpu)
+            .with_padding(2)
+            .with_pixels("small", square(2))
+            .with_pixels("big", square(4))
+            .build();
+        let big = atlas.get("big").unwrap();
+        let small = atlas.get("small").unwrap();
+        assert_eq!((big.width, big.height), (4, 4));
+        assert_eq!((small.width, small.height), (2, 2));
+        // tallest first: big at 2, small after it and its padding
+        assert_eq!(big.region.x * atlas.texture.width as f32, 2.0);
+        assert_eq!(small.region.x * atlas.texture.width as f32, 8.0);
+        assert!(atlas.get("missing").is_none());
+    }
+}
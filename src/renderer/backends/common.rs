@@ -0,0 +1,107 @@
+use crate::renderer::color::Color;
+use crate::renderer::layer::Layer;
+use crate::renderer::texture::{Texture, TextureStorage};
+use crate::renderer::viewport::Viewport;
+use image::RgbaImage;
+use nalgebra_glm as glm;
+
+/// A single textured quad recorded by the `Renderer` during a frame.
+#[derive(Clone, Debug)]
+pub struct DrawCommand {
+    pub texture: Texture,
+    pub position: glm::Vec2,
+    pub size: glm::Vec2,
+    pub rotate: f32,
+    pub color: Color,
+    pub layer: Layer,
+    /// Order within the layer, higher on top.
+    pub depth: f32,
+    /// Screen corners of a free shaped quad, e.g. part of a polygon, used
+    /// instead of `position`, `size` and `rotate`, which only bound it then.
+    /// Triangles repeat their first corner.
+    pub vertices: Option<[glm::Vec2; 4]>,
+}
+impl DrawCommand {
+    /// Model matrix mapping the unit quad onto the screen, rotated around its center.
+    pub fn model(&self) -> glm::Mat4 {
+        let mut model = glm::Mat4x4::from_diagonal_element(1.0);
+        model = glm::translate(&model, &glm::vec3(self.position.x, self.position.y, 0.0));
+        model = glm::translate(
+            &model,
+            &glm::vec3(0.5 * self.size.x, 0.5 * self.size.y, 0.0),
+        ); // move
+        model = glm::rotate(&model, self.rotate, &glm::vec3(0.0, 0.0, 1.0));
+        model = glm::translate(
+            &model,
+            &glm::vec3(-0.5 * self.size.x, -0.5 * self.size.y, 0.0),
+        ); // move
+        glm::scale(&model, &glm::vec3(self.size.x, self.size.y, 1.0))
+    }
+
+    /// Corners of the quad after `model`, or `vertices` when set: top-left,
+    /// top-right, bottom-right, bottom-left.
+    pub fn corners(&self) -> [glm::Vec2; 4] {
+        if let Some(vertices) = self.vertices {
+            return vertices;
+        }
+        let model = self.model();
+        UNIT_QUAD.map(|corner| {
+            let p = model * glm::vec4(corner.x, corner.y, 0.0, 1.0);
+            glm::vec2(p.x, p.y)
+        })
+    }
+
+    /// Texture coordinates matching `corners`, within the texture's region.
+    pub fn tex_coords(&self) -> [glm::Vec2; 4] {
+        let region = self.texture.region;
+        [
+            glm::vec2(region.x, region.y),
+            glm::vec2(region.z, region.y),
+            glm::vec2(region.z, region.w),
+            glm::vec2(region.x, region.w),
+        ]
+    }
+}
+
+const UNIT_QUAD: [glm::Vec2; 4] = [
+    glm::Vec2::new(0.0, 0.0),
+    glm::Vec2::new(1.0, 0.0),
+    glm::Vec2::new(1.0, 1.0),
+    glm::Vec2::new(0.0, 1.0),
+];
+
+/// Per-frame counters reported by a backend.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    /// Quads submitted.
+    pub sprites: usize,
+    /// Runs of consecutive quads sharing a texture.
+    pub batches: usize,
+    /// Draw calls issued; a batch larger than the vertex buffer takes several.
+    pub draw_calls: usize,
+}
+
+/// Consumes the draw commands recorded by the `Renderer` once per frame.
+pub trait RenderBackend {
+    fn clear(&mut self);
+    fn render(&mut self, projection: &glm::Mat4, commands: &[DrawCommand]);
+
+    /// Draws the following frames into `viewport` of a `window` sized
+    /// window, clearing the rest. Backends start with the whole window.
+    fn set_viewport(&mut self, _window: glm::Vec2, _viewport: &Viewport) {}
+
+    /// Counters of the last rendered frame.
+    fn stats(&self) -> RenderStats {
+        RenderStats::default()
+    }
+
+    /// Copy of the last rendered frame, for backends that can read it back.
+    fn snapshot(&self) -> Option<RgbaImage> {
+        None
+    }
+
+    /// Where the textures this backend draws are created.
+    fn texture_storage(&self) -> TextureStorage {
+        TextureStorage::Gpu
+    }
+}
@@ -0,0 +1,199 @@
+use crate::renderer::assets::Handle;
+use crate::renderer::backends::common::{DrawCommand, RenderBackend, RenderStats};
+use crate::renderer::gl_objects::{MyTypes, VertexBufferElement, VertexesLayout, EBO, VAO, VBO};
+use crate::renderer::shader::Shader;
+use crate::renderer::viewport::Viewport;
+use gl::types::*;
+use nalgebra_glm as glm;
+use std::mem;
+use std::ptr;
+
+pub(crate) const SPRITE_SHADER: &str = "resources/shaders/sprite.shader";
+/// Quads the vertex buffer holds before a batch has to be drawn.
+const MAX_SPRITES: usize = 1000;
+// pos(2) + tex(2) + color(4)
+const FLOATS_PER_VERTEX: usize = 8;
+const FLOATS_PER_SPRITE: usize = 4 * FLOATS_PER_VERTEX;
+
+/// Accumulates quads into a dynamic vertex buffer and draws them with
+/// `sprite.shader`, one draw call per texture change.
+pub struct GlBackend {
+    shader: Handle<Shader>,
+    gl_objects: GlObjects,
+    vertices: Vec<f32>,
+    stats: RenderStats,
+    // x, y, width, height for gl::Viewport, from the bottom left corner
+    viewport: Option<(i32, i32, i32, i32)>,
+}
+impl GlBackend {
+    /// `shader` is expected to be `SPRITE_SHADER`, or share its vertex layout.
+    pub fn new(shader: Handle<Shader>) -> Self {
+        let indices = (0..MAX_SPRITES as i32)
+            .flat_map(|sprite| {
+                let i = sprite * 4;
+                [i, i + 1, i + 2, i + 2, i + 3, i]
+            })
+            .collect();
+        let gl_objects = GlObjectsBuilder::new()
+            .dynamic_vertices(MAX_SPRITES * FLOATS_PER_SPRITE)
+            .indices(indices)
+            .layout(MyTypes::FLOAT, 2)
+            .layout(MyTypes::FLOAT, 2)
+            .layout(MyTypes::FLOAT, 4)
+            .build();
+        Self {
+            shader,
+            gl_objects,
+            vertices: Vec::with_capacity(MAX_SPRITES * FLOATS_PER_SPRITE),
+            stats: RenderStats::default(),
+            viewport: None,
+        }
+    }
+
+    fn push(&mut self, command: &DrawCommand) {
+        let color = command.color;
+        for (pos, tex) in command.corners().iter().zip(command.tex_coords()) {
+            self.vertices.extend_from_slice(&[
+                pos.x, pos.y, tex.x, tex.y, color.r, color.g, color.b, color.a,
+            ]);
+        }
+    }
+
+    /// Draws the pending quads with the currently bound texture.
+    fn flush(&mut self) {
+        if self.vertices.is_empty() {
+            return;
+        }
+        let sprites = self.vertices.len() / FLOATS_PER_SPRITE;
+        self.gl_objects.vbo.update(&self.vertices);
+        unsafe {
+            gl::DrawElements(
+                gl::TRIANGLES,
+                (sprites * 6) as GLsizei,
+                gl::UNSIGNED_INT,
+                ptr::null(),
+            );
+        }
+        self.stats.draw_calls += 1;
+        self.vertices.clear();
+    }
+}
+
+impl RenderBackend for GlBackend {
+    fn clear(&mut self) {
+        unsafe {
+            gl::ClearColor(0.0, 0.0, 0.0, 0.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+            if let Some((x, y, width, height)) = self.viewport {
+                gl::Viewport(x, y, width, height);
+            }
+        }
+    }
+
+    fn set_viewport(&mut self, window: glm::Vec2, viewport: &Viewport) {
+        let bottom = window.y - viewport.position.y - viewport.size.y;
+        self.viewport = Some((
+            viewport.position.x as i32,
+            bottom as i32,
+            viewport.size.x as i32,
+            viewport.size.y as i32,
+        ));
+    }
+
+    fn render(&mut self, projection: &glm::Mat4, commands: &[DrawCommand]) {
+        self.shader.activate();
+        self.shader.set_uniform_1i("image", 0);
+        self.shader.set_matrix4("projection", projection);
+
+        self.stats = RenderStats::default();
+        self.gl_objects.vao.bind();
+        let mut bound_texture = None;
+        for command in commands {
+            if bound_texture != Some(command.texture.id()) {
+                self.flush();
+                command.texture.bind();
+                bound_texture = Some(command.texture.id());
+                self.stats.batches += 1;
+            } else if self.vertices.len() == MAX_SPRITES * FLOATS_PER_SPRITE {
+                self.flush();
+            }
+            self.push(command);
+            self.stats.sprites += 1;
+        }
+        self.flush();
+        self.gl_objects.vao.unbind();
+    }
+
+    fn stats(&self) -> RenderStats {
+        self.stats
+    }
+}
+
+pub struct GlObjects {
+    _indices: Vec<i32>,
+    vao: VAO,
+    vbo: VBO,
+    // recorded in the VAO, only kept so it lives as long as it
+    _ebo: Option<EBO>,
+}
+
+struct GlObjectsBuilder {
+    indices: Vec<i32>,
+    vao: VAO,
+    vbo: Option<VBO>,
+    ebo: Option<EBO>,
+    layouts: VertexesLayout,
+}
+impl GlObjectsBuilder {
+    pub fn new() -> Self {
+        let vao = VAO::new();
+        vao.bind();
+        Self {
+            indices: Vec::new(),
+            vao,
+            vbo: None,
+            ebo: None,
+            layouts: VertexesLayout::new(),
+        }
+    }
+
+    /// Vertex buffer of `capacity` floats, streamed every frame.
+    fn dynamic_vertices(mut self, capacity: usize) -> Self {
+        self.vbo = Some(VBO::dynamic(capacity));
+        self
+    }
+
+    fn indices(mut self, indices: Vec<i32>) -> Self {
+        self.indices = indices;
+        self.ebo = Some(EBO::new(&self.indices[..]));
+        self
+    }
+
+    fn layout(mut self, tp: MyTypes, size: usize) -> Self {
+        let el = VertexBufferElement::new(size, tp, false);
+        self.layouts.elements.push(el);
+        self.layouts.stride += match tp {
+            MyTypes::FLOAT => mem::size_of::<GLfloat>(),
+            MyTypes::INT => mem::size_of::<GLsizei>(),
+            MyTypes::CHAR => mem::size_of::<GLchar>(),
+        } as GLsizei
+            * size as GLsizei;
+        self
+    }
+
+    fn build(self) -> GlObjects {
+        self.vao.link(&self.vbo.as_ref().unwrap(), &self.layouts);
+        self.vao.unbind();
+        self.vbo.as_ref().unwrap().unbind();
+        if self.ebo.is_some() {
+            self.ebo.as_ref().unwrap().unbind();
+        }
+
+        GlObjects {
+            _indices: self.indices,
+            vao: self.vao,
+            vbo: self.vbo.unwrap(),
+            _ebo: self.ebo,
+        }
+    }
+}
@@ -0,0 +1,4 @@
+pub mod common;
+pub mod gl_backend;
+pub mod recording_backend;
+pub mod software_backend;
@@ -0,0 +1,187 @@
+use crate::renderer::backends::common::{DrawCommand, RenderBackend};
+use crate::renderer::texture::TextureStorage;
+use nalgebra_glm as glm;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// The frames rendered so far, oldest first.
+pub type Recording = Rc<RefCell<Vec<Vec<DrawCommand>>>>;
+
+/// Keeps the commands of the frames in memory instead of drawing them,
+/// so tests can assert on what a game drew.
+#[derive(Default)]
+pub struct RecordingBackend {
+    frames: Recording,
+    last_only: bool,
+}
+impl RecordingBackend {
+    /// Keeps every frame, for tests that look back at earlier ones.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps only the frame rendered last, so long runs stay in constant memory.
+    pub fn last_frame() -> Self {
+        Self {
+            last_only: true,
+            ..Self::default()
+        }
+    }
+
+    /// Shared view of the recorded frames, still readable once the backend
+    /// has been handed over to a `Renderer`.
+    pub fn frames(&self) -> Recording {
+        Rc::clone(&self.frames)
+    }
+}
+
+impl RenderBackend for RecordingBackend {
+    fn clear(&mut self) {}
+
+    fn render(&mut self, _projection: &glm::Mat4, commands: &[DrawCommand]) {
+        let mut frames = self.frames.borrow_mut();
+        if self.last_only {
+            frames.clear();
+        }
+        frames.push(commands.to_vec());
+    }
+
+    fn texture_storage(&self) -> TextureStorage {
+        TextureStorage::Cpu
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::camera::Space;
+    use crate::renderer::color::Color;
+    use crate::renderer::draw_params::DrawParams;
+    use crate::renderer::layer::Layer;
+    use crate::renderer::testing::{pixel, recorded};
+    use crate::renderer::Renderer;
+
+    fn square(x: f32, y: f32) -> DrawParams {
+        DrawParams::at(glm::vec2(x, y)).with_size(glm::vec2(10.0, 10.0))
+    }
+
+    #[test]
+    fn positions_are_recorded_after_the_camera() {
+        let (mut renderer, frames) = recorded();
+        let texture = pixel();
+        renderer.clear();
+        renderer.camera.position += glm::vec2(100.0, 50.0);
+        renderer.draw(&texture, &square(100.0, 50.0));
+        renderer.set_parallax(Layer::Background, glm::vec2(0.0, 0.0));
+        renderer.draw(&texture, &square(100.0, 50.0).with_layer(Layer::Background));
+        renderer.set_space(Space::Screen);
+        renderer.draw(&texture, &square(100.0, 50.0).with_layer(Layer::Ui));
+        renderer.flush();
+
+        renderer.clear();
+        renderer.set_space(Space::World);
+        renderer.camera.position = glm::vec2(160.0, 120.0);
+        renderer.camera.zoom = 2.0;
+        renderer.draw(&texture, &square(155.0, 115.0));
+        renderer.flush();
+
+        let frames = frames.borrow();
+        let placed = |frame: usize| -> Vec<(glm::Vec2, glm::Vec2)> {
+            frames[frame]
+                .iter()
+                .map(|command| (command.position, command.size))
+                .collect()
+        };
+        let size = glm::vec2(10.0, 10.0);
+        assert_eq!(
+            placed(0),
+            [
+                // parallax 0: stays where a still camera shows it
+                (glm::vec2(100.0, 50.0), size),
+                (glm::vec2(0.0, 0.0), size),
+                (glm::vec2(100.0, 50.0), size),
+            ]
+        );
+        // zoomed around the center of the screen
+        assert_eq!(placed(1), [(glm::vec2(150.0, 110.0), size * 2.0)]);
+    }
+
+    #[test]
+    fn colors_are_recorded_as_given() {
+        let (mut renderer, frames) = recorded();
+        let texture = pixel();
+        renderer.clear();
+        renderer.draw(&texture, &square(0.0, 0.0));
+        renderer.draw(
+            &texture,
+            &square(0.0, 0.0).with_color(Color::RED).with_alpha(0.5),
+        );
+        renderer.draw_image(
+            glm::vec2(0.0, 0.0),
+            glm::vec2(1.0, 1.0),
+            0.0,
+            glm::vec3(0.0, 1.0, 0.0),
+            &texture,
+        );
+        renderer.flush();
+
+        let colors: Vec<Color> = frames.borrow()[0]
+            .iter()
+            .map(|command| command.color)
+            .collect();
+        assert_eq!(
+            colors,
+            [Color::WHITE, Color::rgba(1.0, 0.0, 0.0, 0.5), Color::GREEN]
+        );
+    }
+
+    #[test]
+    fn each_flush_records_a_frame_in_layer_order() {
+        let (mut renderer, frames) = recorded();
+        let texture = pixel();
+        renderer.clear();
+        renderer.set_layer(Layer::Ui);
+        renderer.draw(&texture, &square(0.0, 0.0));
+        renderer.set_layer(Layer::Tiles);
+        renderer.draw(&texture, &square(1.0, 0.0));
+        renderer.draw(&texture, &square(2.0, 0.0).with_layer(Layer::Foreground));
+        renderer.draw(&texture, &square(3.0, 0.0).with_layer(Layer::Background));
+        renderer.flush();
+        renderer.clear();
+        renderer.flush();
+
+        let frames = frames.borrow();
+        assert_eq!(frames.len(), 2);
+        let layers: Vec<(Layer, f32)> = frames[0]
+            .iter()
+            .map(|command| (command.layer, command.position.x))
+            .collect();
+        assert_eq!(
+            layers,
+            [
+                (Layer::Background, 3.0),
+                (Layer::Tiles, 1.0),
+                (Layer::Foreground, 2.0),
+                (Layer::Ui, 0.0),
+            ]
+        );
+        assert!(frames[1].is_empty());
+    }
+
+    #[test]
+    fn last_frame_keeps_a_single_frame() {
+        let backend = RecordingBackend::last_frame();
+        let frames = backend.frames();
+        let mut renderer = Renderer::with_backend(320, 240, Box::new(backend));
+        let texture = pixel();
+        for x in 0..3 {
+            renderer.clear();
+            renderer.draw(&texture, &square(x as f32, 0.0));
+            renderer.flush();
+        }
+
+        let frames = frames.borrow();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0][0].position.x, 2.0);
+    }
+}
@@ -0,0 +1,358 @@
+use crate::renderer::backends::common::{DrawCommand, RenderBackend};
+use crate::renderer::texture::TextureStorage;
+use crate::renderer::viewport::Viewport;
+use image::{Rgba, RgbaImage};
+use nalgebra_glm as glm;
+use std::path::Path;
+
+/// Rasterizes draw commands into an in-memory RGBA frame, without a GPU.
+///
+/// Mirrors what `sprite.shader` does on the GL path: nearest texel sampling,
+/// multiplied by the tint, alpha blended over the frame. Textures are read
+/// through `Texture::pixels`, so they must be `TextureStorage::Cpu` ones.
+pub struct SoftwareBackend {
+    frame: RgbaImage,
+    viewport: Viewport,
+}
+impl SoftwareBackend {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            frame: RgbaImage::new(width, height),
+            viewport: Viewport::full(glm::vec2(width as f32, height as f32)),
+        }
+    }
+
+    /// The last rendered frame.
+    pub fn frame(&self) -> &RgbaImage {
+        &self.frame
+    }
+
+    pub fn save_png(&self, path: &str) -> image::ImageResult<()> {
+        self.frame.save(Path::new(path))
+    }
+
+    fn draw_command(&mut self, projection: &glm::Mat4, command: &DrawCommand) {
+        let Some(pixels) = command.texture.pixels() else {
+            log::warn!(
+                "Texture {} has no CPU pixels, skipped",
+                command.texture.id()
+            );
+            return;
+        };
+        let Viewport { position, size } = self.viewport;
+        let mut corners =
+            command
+                .corners()
+                .into_iter()
+                .zip(command.tex_coords())
+                .map(|(corner, uv)| {
+                    let ndc = projection * glm::vec4(corner.x, corner.y, 0.0, 1.0);
+                    Vertex {
+                        screen: position
+                            + glm::vec2((ndc.x + 1.0) * 0.5 * size.x, (1.0 - ndc.y) * 0.5 * size.y),
+                        uv,
+                    }
+                });
+        let corners: [Vertex; 4] = std::array::from_fn(|_| corners.next().unwrap());
+
+        let tint = command.color;
+        let mut shade = |uv: glm::Vec2| {
+            let x = ((uv.x * pixels.width() as f32) as u32).min(pixels.width() - 1);
+            let y = ((uv.y * pixels.height() as f32) as u32).min(pixels.height() - 1);
+            let Rgba([r, g, b, a]) = *pixels.get_pixel(x, y);
+            glm::vec4(
+                r as f32 / 255.0 * tint.r,
+                g as f32 / 255.0 * tint.g,
+                b as f32 / 255.0 * tint.b,
+                a as f32 / 255.0 * tint.a,
+            )
+        };
+        rasterize(
+            &mut self.frame,
+            [&corners[0], &corners[1], &corners[2]],
+            &mut shade,
+        );
+        rasterize(
+            &mut self.frame,
+            [&corners[2], &corners[3], &corners[0]],
+            &mut shade,
+        );
+    }
+}
+
+impl RenderBackend for SoftwareBackend {
+    fn clear(&mut self) {
+        for pixel in self.frame.pixels_mut() {
+            *pixel = Rgba([0, 0, 0, 0]);
+        }
+    }
+
+    /// The frame keeps the size it was created with; only where the picture
+    /// lands in it changes.
+    fn set_viewport(&mut self, _window: glm::Vec2, viewport: &Viewport) {
+        self.viewport = *viewport;
+    }
+
+    fn render(&mut self, projection: &glm::Mat4, commands: &[DrawCommand]) {
+        for command in commands {
+            self.draw_command(projection, command);
+        }
+    }
+
+    fn snapshot(&self) -> Option<RgbaImage> {
+        Some(self.frame.clone())
+    }
+
+    fn texture_storage(&self) -> TextureStorage {
+        TextureStorage::Cpu
+    }
+}
+
+struct Vertex {
+    screen: glm::Vec2,
+    uv: glm::Vec2,
+}
+
+/// Fills the triangle using pixel centers and the top-left rule, so the two
+/// triangles of a quad never blend their shared edge twice.
+fn rasterize(
+    frame: &mut RgbaImage,
+    triangle: [&Vertex; 3],
+    shade: &mut impl FnMut(glm::Vec2) -> glm::Vec4,
+) {
+    let [mut a, b, mut c] = triangle;
+    let mut area = edge(&a.screen, &b.screen, &c.screen);
+    if area == 0.0 {
+        return;
+    }
+    if area < 0.0 {
+        std::mem::swap(&mut a, &mut c);
+        area = -area;
+    }
+
+    let min_x = a.screen.x.min(b.screen.x).min(c.screen.x).floor().max(0.0) as u32;
+    let min_y = a.screen.y.min(b.screen.y).min(c.screen.y).floor().max(0.0) as u32;
+    let max_x = (a.screen.x.max(b.screen.x).max(c.screen.x).ceil() as u32).min(frame.width());
+    let max_y = (a.screen.y.max(b.screen.y).max(c.screen.y).ceil() as u32).min(frame.height());
+
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let p = glm::vec2(x as f32 + 0.5, y as f32 + 0.5);
+            let w0 = edge(&b.screen, &c.screen, &p);
+            let w1 = edge(&c.screen, &a.screen, &p);
+            let w2 = edge(&a.screen, &b.screen, &p);
+            if !covers(w0, &b.screen, &c.screen)
+                || !covers(w1, &c.screen, &a.screen)
+                || !covers(w2, &a.screen, &b.screen)
+            {
+                continue;
+            }
+            let uv = (a.uv * w0 + b.uv * w1 + c.uv * w2) / area;
+            blend(frame.get_pixel_mut(x, y), shade(uv));
+        }
+    }
+}
+
+/// Walked from the same end either way, so the two triangles sharing an edge
+/// get exactly opposite weights and never both drop a pixel center on it.
+fn edge(from: &glm::Vec2, to: &glm::Vec2, p: &glm::Vec2) -> f32 {
+    if (from.x, from.y) > (to.x, to.y) {
+        return -edge(to, from, p);
+    }
+    (to.x - from.x) * (p.y - from.y) - (to.y - from.y) * (p.x - from.x)
+}
+
+fn covers(weight: f32, from: &glm::Vec2, to: &glm::Vec2) -> bool {
+    if weight != 0.0 {
+        return weight > 0.0;
+    }
+    // top edge (horizontal, going right in y-down space) or left edge (going up)
+    let d = to - from;
+    (d.y == 0.0 && d.x > 0.0) || d.y < 0.0
+}
+
+/// `glBlendFunc(GL_SRC_ALPHA, GL_ONE_MINUS_SRC_ALPHA)` on all four channels.
+fn blend(dst: &mut Rgba<u8>, src: glm::Vec4) {
+    let alpha = src.w.clamp(0.0, 1.0);
+    for (i, channel) in dst.0.iter_mut().enumerate() {
+        let d = *channel as f32 / 255.0;
+        let s = src[i].clamp(0.0, 1.0);
+        *channel = ((s * alpha + d * (1.0 - alpha)) * 255.0).round() as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::camera::Space;
+    use crate::renderer::color::Color;
+    use crate::renderer::draw_params::DrawParams;
+    use crate::renderer::texture::{Texture, TextureStorage};
+    use crate::renderer::{ImgKind, Renderer};
+    use image::DynamicImage;
+
+    const PALETTE: [(char, [u8; 4]); 8] = [
+        ('.', [0, 0, 0, 0]),
+        ('R', [255, 0, 0, 255]),
+        ('G', [0, 255, 0, 255]),
+        ('B', [0, 0, 255, 255]),
+        ('Y', [255, 255, 0, 255]),
+        // half transparent red over nothing
+        ('r', [128, 0, 0, 64]),
+        // half transparent red over blue
+        ('P', [128, 0, 128, 191]),
+        ('W', [255, 255, 255, 255]),
+    ];
+
+    fn color_of(c: char) -> Rgba<u8> {
+        let (_, rgba) = PALETTE.iter().find(|(key, _)| *key == c).unwrap();
+        Rgba(*rgba)
+    }
+
+    fn image(rows: &[&str]) -> RgbaImage {
+        RgbaImage::from_fn(rows[0].len() as u32, rows.len() as u32, |x, y| {
+            color_of(rows[y as usize].as_bytes()[x as usize] as char)
+        })
+    }
+
+    fn texture(rows: &[&str]) -> Texture {
+        Texture::new(
+            DynamicImage::ImageRgba8(image(rows)),
+            ImgKind::PNG,
+            TextureStorage::Cpu,
+        )
+    }
+
+    /// The frame drawn by `draw`, in palette characters so a failure shows
+    /// the picture. Pixels missing from the palette read as `?`.
+    fn render(width: u32, height: u32, draw: impl FnOnce(&mut Renderer)) -> Vec<String> {
+        let backend = SoftwareBackend::new(width, height);
+        let mut renderer = Renderer::with_backend(width, height, Box::new(backend));
+        renderer.set_space(Space::Screen);
+        renderer.clear();
+        draw(&mut renderer);
+        renderer.flush();
+        let frame = renderer.snapshot().unwrap();
+        frame
+            .rows()
+            .map(|row| {
+                row.map(|pixel| {
+                    PALETTE
+                        .iter()
+                        .find(|(_, rgba)| *rgba == pixel.0)
+                        .map_or('?', |(c, _)| *c)
+                })
+                .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tint_and_alpha_blend_over_the_frame() {
+        let white = texture(&["WW", "W."]);
+        let frame = render(4, 4, |renderer| {
+            renderer.draw(
+                &white,
+                &DrawParams::at(glm::vec2(0.0, 0.0))
+                    .with_size(glm::vec2(4.0, 2.0))
+                    .with_color(Color::BLUE),
+            );
+            renderer.draw(
+                &white,
+                &DrawParams::at(glm::vec2(0.0, 0.0))
+                    .with_size(glm::vec2(4.0, 4.0))
+                    .with_color(Color::RED)
+                    .with_alpha(0.5)
+                    .with_depth(1.0),
+            );
+        });
+        // the transparent texel keeps the frame as it was
+        assert_eq!(frame, ["PPPP", "PPrr", "rr..", "rr.."]);
+    }
+
+    #[test]
+    fn source_rects_and_flips_pick_texels() {
+        let strip = texture(&["RGBY"]);
+        let frame = render(4, 2, |renderer| {
+            renderer.draw(
+                &strip,
+                &DrawParams::at(glm::vec2(0.0, 0.0)).with_source(1, 0, 2, 1),
+            );
+            // flipped around its top left corner, so drawn left of it
+            renderer.draw(
+                &strip,
+                &DrawParams::at(glm::vec2(4.0, 1.0))
+                    .with_source(2, 0, 2, 1)
+                    .with_size(glm::vec2(4.0, 1.0))
+                    .with_flip_x(true),
+            );
+        });
+        assert_eq!(frame, ["GB..", "YYBB"]);
+    }
+
+    #[test]
+    fn rotation_turns_around_the_pivot() {
+        let halves = texture(&["RRGG", "RRGG"]);
+        let frame = render(6, 6, |renderer| {
+            renderer.draw(
+                &halves,
+                &DrawParams::at(glm::vec2(3.0, 3.0))
+                    .with_pivot(glm::vec2(0.5, 0.5))
+                    .with_rotation(std::f32::consts::FRAC_PI_2),
+            );
+        });
+        // y points down, so a quarter turn takes the right half below
+        assert_eq!(
+            frame,
+            ["......", "..RR..", "..RR..", "..GG..", "..GG..", "......"]
+        );
+    }
+
+    #[test]
+    fn edges_are_filled_once() {
+        let white = texture(&["W"]);
+        let half_red = |position: glm::Vec2, size: glm::Vec2| {
+            DrawParams::at(position)
+                .with_size(size)
+                .with_color(Color::RED)
+                .with_alpha(0.5)
+        };
+        let frame = render(5, 3, |renderer| {
+            // side by side: the shared edge, and each quad's diagonal, are
+            // blended a single time
+            renderer.draw(&white, &half_red(glm::vec2(0.0, 0.0), glm::vec2(2.0, 2.0)));
+            renderer.draw(&white, &half_red(glm::vec2(2.0, 0.0), glm::vec2(2.0, 2.0)));
+        });
+        assert_eq!(frame, ["rrrr.", "rrrr.", "....."]);
+
+        // pixel centers on the left and top edges are in, on the right and
+        // bottom ones out
+        let frame = render(4, 4, |renderer| {
+            renderer.draw(&white, &half_red(glm::vec2(0.5, 0.5), glm::vec2(2.0, 2.0)));
+        });
+        assert_eq!(frame, ["rr..", "rr..", "....", "...."]);
+    }
+
+    #[test]
+    fn diagonals_leave_no_holes_at_fractional_positions() {
+        let green = texture(&["G"]);
+        // 1.5x tiles: pixel centers land exactly on the diagonals of some of
+        // them, which both triangles of the quad must agree on
+        let frame = render(48, 48, |renderer| {
+            for y in 0..16 {
+                for x in 0..16 {
+                    let position = glm::vec2(x as f32, y as f32) * 4.5 - glm::vec2(1.25, 0.75);
+                    renderer.draw(
+                        &green,
+                        &DrawParams::at(position).with_size(glm::vec2(4.5, 4.5)),
+                    );
+                }
+            }
+        });
+        assert!(
+            frame.iter().all(|row| row.chars().all(|c| c == 'G')),
+            "{:#?}",
+            frame
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use nalgebra_glm as glm;
+
+/// Coordinate space draw calls are given in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Space {
+    /// Moved by the camera: levels, entities.
+    World,
+    /// Screen pixels, ignoring the camera: HUD, menus. With a virtual
+    /// resolution, its pixels rather than the window's.
+    Screen,
+}
+
+/// A 2D camera. `position` is the world point shown at the center of the viewport.
+///
+/// A new camera maps world coordinates 1:1 to screen pixels, so games that
+/// never touch it draw exactly as before.
+#[derive(Clone, Debug)]
+pub struct Camera2D {
+    pub position: glm::Vec2,
+    pub zoom: f32,
+    /// Radians, counterclockwise.
+    pub rotation: f32,
+    viewport: glm::Vec2,
+    deadzone: glm::Vec2,
+    bounds: Option<(glm::Vec2, glm::Vec2)>,
+}
+impl Camera2D {
+    pub fn new(viewport_width: f32, viewport_height: f32) -> Self {
+        let viewport = glm::vec2(viewport_width, viewport_height);
+        Self {
+            position: viewport * 0.5,
+            zoom: 1.0,
+            rotation: 0.0,
+            viewport,
+            deadzone: glm::vec2(0.0, 0.0),
+            bounds: None,
+        }
+    }
+
+    /// Size in world units of the box around `position` a followed target can
+    /// move in without the camera moving.
+    pub fn set_deadzone(&mut self, width: f32, height: f32) {
+        self.deadzone = glm::vec2(width, height);
+    }
+
+    /// World rectangle (min, max) the camera never shows beyond, typically the level.
+    pub fn set_bounds(&mut self, bounds: Option<(glm::Vec2, glm::Vec2)>) {
+        self.bounds = bounds;
+        self.clamp_to_bounds();
+    }
+
+    pub fn set_viewport(&mut self, width: f32, height: f32) {
+        self.viewport = glm::vec2(width, height);
+    }
+
+    /// Changes the viewport keeping the world point at its top left corner,
+    /// so a camera that maps world to screen 1:1 still does once the screen
+    /// grows. Rotation is not taken into account.
+    pub fn resize_viewport(&mut self, width: f32, height: f32) {
+        let viewport = glm::vec2(width, height);
+        self.position += (viewport - self.viewport) * (0.5 / self.zoom);
+        self.viewport = viewport;
+        self.clamp_to_bounds();
+    }
+
+    pub fn viewport(&self) -> glm::Vec2 {
+        self.viewport
+    }
+
+    /// World to screen transform.
+    pub fn view(&self) -> glm::Mat4 {
+        let mut view = glm::Mat4x4::from_diagonal_element(1.0);
+        view = glm::translate(
+            &view,
+            &glm::vec3(0.5 * self.viewport.x, 0.5 * self.viewport.y, 0.0),
+        );
+        view = glm::rotate(&view, -self.rotation, &glm::vec3(0.0, 0.0, 1.0));
+        view = glm::scale(&view, &glm::vec3(self.zoom, self.zoom, 1.0));
+        glm::translate(&view, &glm::vec3(-self.position.x, -self.position.y, 0.0))
+    }
+
+    pub fn world_to_screen(&self, point: glm::Vec2) -> glm::Vec2 {
+        let p = self.view() * glm::vec4(point.x, point.y, 0.0, 1.0);
+        glm::vec2(p.x, p.y)
+    }
+
+    pub fn screen_to_world(&self, point: glm::Vec2) -> glm::Vec2 {
+        let p = glm::inverse(&self.view()) * glm::vec4(point.x, point.y, 0.0, 1.0);
+        glm::vec2(p.x, p.y)
+    }
+
+    /// World rectangle (min, max) covering everything on screen.
+    pub fn visible_area(&self) -> (glm::Vec2, glm::Vec2) {
+        let corners = [
+            glm::vec2(0.0, 0.0),
+            glm::vec2(self.viewport.x, 0.0),
+            glm::vec2(self.viewport.x, self.viewport.y),
+            glm::vec2(0.0, self.viewport.y),
+        ]
+        .map(|corner| self.screen_to_world(corner));
+        let mut min = corners[0];
+        let mut max = corners[0];
+        for corner in &corners[1..] {
+            min = glm::min2(&min, corner);
+            max = glm::max2(&max, corner);
+        }
+        (min, max)
+    }
+
+    /// Moves the camera just enough to keep `target` inside the deadzone,
+    /// then clamps it to the bounds.
+    pub fn follow(&mut self, target: glm::Vec2) {
+        let half = self.deadzone * 0.5;
+        for axis in 0..2 {
+            let offset = target[axis] - self.position[axis];
+            if offset > half[axis] {
+                self.position[axis] = target[axis] - half[axis];
+            } else if offset < -half[axis] {
+                self.position[axis] = target[axis] + half[axis];
+            }
+        }
+        self.clamp_to_bounds();
+    }
+
+    /// Keeps the visible area inside the bounds, centering on any axis where
+    /// the bounds are smaller than the view. Rotation is not taken into account.
+    pub fn clamp_to_bounds(&mut self) {
+        let Some((min, max)) = self.bounds else {
+            return;
+        };
+        let half = self.viewport / (2.0 * self.zoom);
+        for axis in 0..2 {
+            self.position[axis] = if max[axis] - min[axis] <= 2.0 * half[axis] {
+                0.5 * (min[axis] + max[axis])
+            } else {
+                self.position[axis].clamp(min[axis] + half[axis], max[axis] - half[axis])
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: glm::Vec2, b: glm::Vec2) -> bool {
+        glm::distance(&a, &b) < 1e-3
+    }
+
+    #[test]
+    fn the_deadzone_lets_the_target_move_before_the_camera_does() {
+        let mut camera = Camera2D::new(320.0, 240.0);
+        camera.position = glm::vec2(100.0, 100.0);
+        camera.set_deadzone(40.0, 20.0);
+        camera.follow(glm::vec2(115.0, 92.0));
+        assert_eq!(camera.position, glm::vec2(100.0, 100.0));
+        // pushed along by the edge the target crossed
+        camera.follow(glm::vec2(130.0, 100.0));
+        assert_eq!(camera.position, glm::vec2(110.0, 100.0));
+        camera.follow(glm::vec2(110.0, 60.0));
+        assert_eq!(camera.position, glm::vec2(110.0, 70.0));
+
+        camera.set_deadzone(0.0, 0.0);
+        camera.follow(glm::vec2(-5.0, 7.0));
+        assert_eq!(camera.position, glm::vec2(-5.0, 7.0));
+    }
+
+    #[test]
+    fn bounds_stop_the_camera_at_the_level_edges() {
+        let mut camera = Camera2D::new(320.0, 240.0);
+        camera.set_bounds(Some((glm::vec2(0.0, 0.0), glm::vec2(1000.0, 500.0))));
+        camera.follow(glm::vec2(-50.0, -50.0));
+        assert_eq!(camera.position, glm::vec2(160.0, 120.0));
+        camera.follow(glm::vec2(2000.0, 2000.0));
+        assert_eq!(camera.position, glm::vec2(840.0, 380.0));
+        camera.follow(glm::vec2(500.0, 250.0));
+        assert_eq!(camera.position, glm::vec2(500.0, 250.0));
+
+        // zoomed in, the view is half as large
+        camera.zoom = 2.0;
+        camera.follow(glm::vec2(0.0, 0.0));
+        assert_eq!(camera.position, glm::vec2(80.0, 60.0));
+        let (min, _) = camera.visible_area();
+        assert!(close(min, glm::vec2(0.0, 0.0)));
+    }
+
+    #[test]
+    fn levels_smaller_than_the_view_are_centered() {
+        let mut camera = Camera2D::new(320.0, 240.0);
+        camera.set_bounds(Some((glm::vec2(0.0, 0.0), glm::vec2(200.0, 1000.0))));
+        camera.follow(glm::vec2(500.0, 500.0));
+        assert_eq!(camera.position, glm::vec2(100.0, 500.0));
+        camera.follow(glm::vec2(-500.0, 0.0));
+        assert_eq!(camera.position, glm::vec2(100.0, 120.0));
+    }
+
+    #[test]
+    fn screen_and_world_points_map_both_ways() {
+        let mut camera = Camera2D::new(320.0, 240.0);
+        // a new camera is 1:1
+        let point = glm::vec2(12.0, 34.0);
+        assert!(close(camera.world_to_screen(point), point));
+        assert!(close(camera.screen_to_world(point), point));
+        assert_eq!(
+            camera.visible_area(),
+            (glm::vec2(0.0, 0.0), glm::vec2(320.0, 240.0))
+        );
+
+        camera.position = glm::vec2(500.0, 300.0);
+        camera.zoom = 2.0;
+        // the position is at the center, the rest twice as far from it
+        assert!(close(
+            camera.world_to_screen(glm::vec2(500.0, 300.0)),
+            glm::vec2(160.0, 120.0)
+        ));
+        assert!(close(
+            camera.world_to_screen(glm::vec2(510.0, 290.0)),
+            glm::vec2(180.0, 100.0)
+        ));
+        let (min, max) = camera.visible_area();
+        assert!(close(min, glm::vec2(420.0, 240.0)) && close(max, glm::vec2(580.0, 360.0)));
+
+        // a quarter turn counterclockwise shows the world's x going up the screen
+        camera.rotation = std::f32::consts::FRAC_PI_2;
+        camera.zoom = 1.0;
+        let screen = camera.world_to_screen(glm::vec2(510.0, 300.0));
+        assert!(close(screen, glm::vec2(160.0, 110.0)), "{:?}", screen);
+        assert!(close(
+            camera.screen_to_world(screen),
+            glm::vec2(510.0, 300.0)
+        ));
+        let (min, max) = camera.visible_area();
+        assert!(close(min, glm::vec2(380.0, 140.0)) && close(max, glm::vec2(620.0, 460.0)));
+    }
+
+    #[test]
+    fn resizing_keeps_the_top_left_corner() {
+        let mut camera = Camera2D::new(320.0, 240.0);
+        camera.resize_viewport(640.0, 480.0);
+        assert_eq!(camera.position, glm::vec2(320.0, 240.0));
+        assert!(close(
+            camera.screen_to_world(glm::vec2(0.0, 0.0)),
+            glm::Vec2::zeros()
+        ));
+    }
+}
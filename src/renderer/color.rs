@@ -0,0 +1,113 @@
+use nalgebra_glm as glm;
+
+/// A color with straight (not premultiplied) alpha, channels from 0 to 1.
+/// Sprites and text are multiplied by it, so white draws them unchanged.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const WHITE: Color = Color::rgb(1.0, 1.0, 1.0);
+    pub const BLACK: Color = Color::rgb(0.0, 0.0, 0.0);
+    pub const TRANSPARENT: Color = Color::rgba(0.0, 0.0, 0.0, 0.0);
+    pub const GRAY: Color = Color::rgb(0.5, 0.5, 0.5);
+    pub const RED: Color = Color::rgb(1.0, 0.0, 0.0);
+    pub const GREEN: Color = Color::rgb(0.0, 1.0, 0.0);
+    pub const BLUE: Color = Color::rgb(0.0, 0.0, 1.0);
+    pub const YELLOW: Color = Color::rgb(1.0, 1.0, 0.0);
+    pub const ORANGE: Color = Color::rgb(1.0, 0.65, 0.0);
+    pub const CYAN: Color = Color::rgb(0.0, 1.0, 1.0);
+    pub const MAGENTA: Color = Color::rgb(1.0, 0.0, 1.0);
+
+    pub const fn rgb(r: f32, g: f32, b: f32) -> Self {
+        Self::rgba(r, g, b, 1.0)
+    }
+
+    pub const fn rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// Color from 0-255 channels, as in image editors.
+    pub fn rgba8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self::rgba(
+            r as f32 / 255.0,
+            g as f32 / 255.0,
+            b as f32 / 255.0,
+            a as f32 / 255.0,
+        )
+    }
+
+    /// The same color with alpha `a`, e.g. to fade a sprite out.
+    pub fn with_alpha(self, a: f32) -> Self {
+        Self { a, ..self }
+    }
+
+    /// Channel by channel product, how tints combine.
+    pub fn multiply(self, other: Color) -> Self {
+        Self::rgba(
+            self.r * other.r,
+            self.g * other.g,
+            self.b * other.b,
+            self.a * other.a,
+        )
+    }
+
+    /// A color by name, as text markup writes it: `white`, `black`, `gray`,
+    /// `red`, `green`, `blue`, `yellow`, `orange`, `cyan`, `magenta`, or hex
+    /// `#rrggbb` and `#rrggbbaa`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        if let Some(hex) = name.strip_prefix('#') {
+            return Self::from_hex(hex);
+        }
+        let color = match name.to_ascii_lowercase().as_str() {
+            "white" => Color::WHITE,
+            "black" => Color::BLACK,
+            "gray" | "grey" => Color::GRAY,
+            "red" => Color::RED,
+            "green" => Color::GREEN,
+            "blue" => Color::BLUE,
+            "yellow" => Color::YELLOW,
+            "orange" => Color::ORANGE,
+            "cyan" => Color::CYAN,
+            "magenta" => Color::MAGENTA,
+            _ => return None,
+        };
+        Some(color)
+    }
+
+    fn from_hex(hex: &str) -> Option<Self> {
+        if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        let alpha = if hex.len() == 8 { channel(6)? } else { 255 };
+        Some(Self::rgba8(channel(0)?, channel(2)?, channel(4)?, alpha))
+    }
+
+    pub fn to_vec4(self) -> glm::Vec4 {
+        glm::vec4(self.r, self.g, self.b, self.a)
+    }
+}
+
+impl Default for Color {
+    fn default() -> Self {
+        Color::WHITE
+    }
+}
+
+/// Opaque color from an RGB tint.
+impl From<glm::Vec3> for Color {
+    fn from(rgb: glm::Vec3) -> Self {
+        Self::rgb(rgb.x, rgb.y, rgb.z)
+    }
+}
+
+impl From<glm::Vec4> for Color {
+    fn from(rgba: glm::Vec4) -> Self {
+        Self::rgba(rgba.x, rgba.y, rgba.z, rgba.w)
+    }
+}
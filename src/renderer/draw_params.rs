@@ -0,0 +1,112 @@
+use crate::renderer::color::Color;
+use crate::renderer::layer::Layer;
+use nalgebra_glm as glm;
+
+/// Where and how `Renderer::draw` draws a texture. From `at`, the whole
+/// texture is drawn at its size in pixels with its top left corner at
+/// `position`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DrawParams {
+    pub position: glm::Vec2,
+    /// Size of the quad before `scale`, the source's size in pixels with `None`.
+    pub size: Option<glm::Vec2>,
+    /// Multiplies the size; a negative axis flips the sprite along it.
+    pub scale: glm::Vec2,
+    /// In radians, around the pivot.
+    pub rotation: f32,
+    /// Point of the quad drawn at `position`, which rotation, scale and flips
+    /// keep in place: (0, 0) is the top left corner, (1, 1) the bottom right.
+    pub pivot: glm::Vec2,
+    /// Part of the texture drawn, in pixels: x, y, width, height.
+    pub source: Option<glm::UVec4>,
+    /// Mirrors the sprite left to right.
+    pub flip_x: bool,
+    /// Mirrors the sprite top to bottom.
+    pub flip_y: bool,
+    pub color: Color,
+    /// The renderer's current layer with `None`.
+    pub layer: Option<Layer>,
+    /// Order within the layer, higher on top, e.g. the y of a character's
+    /// feet so the ones in front cover those behind. The renderer's current
+    /// depth with `None`.
+    pub depth: Option<f32>,
+}
+
+impl DrawParams {
+    pub fn at(position: glm::Vec2) -> Self {
+        Self {
+            position,
+            size: None,
+            scale: glm::vec2(1.0, 1.0),
+            rotation: 0.0,
+            pivot: glm::Vec2::zeros(),
+            source: None,
+            flip_x: false,
+            flip_y: false,
+            color: Color::WHITE,
+            layer: None,
+            depth: None,
+        }
+    }
+
+    pub fn with_size(mut self, size: glm::Vec2) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    pub fn with_scale(mut self, scale: glm::Vec2) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// E.g. (0.5, 0.5) to rotate around the center, or (0.5, 1) to place a
+    /// character by its feet.
+    pub fn with_pivot(mut self, pivot: glm::Vec2) -> Self {
+        self.pivot = pivot;
+        self
+    }
+
+    /// Draws the `width` x `height` rectangle at (`x`, `y`) pixels of the
+    /// texture, e.g. a frame of an animation strip.
+    pub fn with_source(mut self, x: u32, y: u32, width: u32, height: u32) -> Self {
+        self.source = Some(glm::vec4(x, y, width, height));
+        self
+    }
+
+    pub fn with_flip_x(mut self, flip: bool) -> Self {
+        self.flip_x = flip;
+        self
+    }
+
+    pub fn with_flip_y(mut self, flip: bool) -> Self {
+        self.flip_y = flip;
+        self
+    }
+
+    /// Tint multiplied with the texture, white by default.
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Keeps the tint, replacing its alpha.
+    pub fn with_alpha(mut self, alpha: f32) -> Self {
+        self.color.a = alpha;
+        self
+    }
+
+    pub fn with_layer(mut self, layer: Layer) -> Self {
+        self.layer = Some(layer);
+        self
+    }
+
+    pub fn with_depth(mut self, depth: f32) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+}
@@ -0,0 +1,245 @@
+//! The text format of AngelCode's BMFont, as written by most bitmap font
+//! generators:
+//!
+//! ```text
+//! info face="Arial" size=32
+//! common lineHeight=36 base=29 pages=1
+//! page id=0 file="arial_0.png"
+//! char id=65 x=0 y=0 width=21 height=23 xoffset=0 yoffset=6 xadvance=21 page=0
+//! kerning first=65 second=86 amount=-2
+//! ```
+//!
+//! Other lines, and keys omak does not use, are skipped.
+
+use super::{Font, Glyph};
+use crate::error::{Error, Result};
+use crate::renderer::texture::{Texture, TextureBuilder, TextureStorage};
+use crate::renderer::ImgKind;
+use nalgebra_glm as glm;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+pub(crate) fn load(path: &str, storage: TextureStorage) -> Result<Font> {
+    let source = fs::read_to_string(path).map_err(|source| Error::Io {
+        path: path.to_string(),
+        source,
+    })?;
+    let directory = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+    parse(&source, path, |file| {
+        let page = directory.join(file);
+        let page = page.to_string_lossy();
+        TextureBuilder::init(&page, ImgKind::from_path(&page))
+            .with_storage(storage)
+            .try_build()
+    })
+}
+
+/// Reads the text format; `path` only names the source in errors, and
+/// `load_page` turns the file of a page into its texture.
+pub(crate) fn parse(
+    source: &str,
+    path: &str,
+    mut load_page: impl FnMut(&str) -> Result<Texture>,
+) -> Result<Font> {
+    let mut font = Font::new(0.0, 0.0, 0.0);
+    let mut pages: HashMap<u32, Texture> = HashMap::new();
+    for (i, line) in source.lines().enumerate() {
+        let config_error = |message| Error::Config {
+            path: path.to_string(),
+            line: i + 1,
+            message,
+        };
+        let (tag, attributes) = split_line(line).map_err(config_error)?;
+        let number = |key: &str| -> std::result::Result<i32, String> {
+            let value = attributes
+                .get(key)
+                .ok_or_else(|| format!("`{}` is missing `{}`", tag, key))?;
+            value
+                .parse()
+                .map_err(|_| format!("`{}` is not a number in `{}={}`", value, key, value))
+        };
+        match tag {
+            // negative when the generator matched the char height rather than the cell
+            "info" => font.size = number("size").map_err(config_error)?.abs() as f32,
+            "common" => {
+                font.line_height = number("lineHeight").map_err(config_error)? as f32;
+                font.baseline = number("base").map_err(config_error)? as f32;
+            }
+            "page" => {
+                let id = number("id").map_err(config_error)? as u32;
+                let file = attributes
+                    .get("file")
+                    .ok_or_else(|| config_error("`page` is missing `file`".to_string()))?;
+                pages.insert(id, load_page(file)?);
+            }
+            "char" => {
+                let (c, glyph) = char_glyph(&number, &pages).map_err(config_error)?;
+                font.insert_glyph(c, glyph);
+            }
+            "kerning" => {
+                let char_of = |key| {
+                    number(key).and_then(|id| {
+                        char::from_u32(id as u32).ok_or_else(|| format!("{} is not a char", id))
+                    })
+                };
+                let (first, second) = (
+                    char_of("first").map_err(config_error)?,
+                    char_of("second").map_err(config_error)?,
+                );
+                let amount = number("amount").map_err(config_error)?;
+                font.insert_kerning(first, second, amount as f32);
+            }
+            _ => {}
+        }
+    }
+    if font.size == 0.0 {
+        font.size = font.line_height;
+    }
+    Ok(font)
+}
+
+fn char_glyph(
+    number: &impl Fn(&str) -> std::result::Result<i32, String>,
+    pages: &HashMap<u32, Texture>,
+) -> std::result::Result<(char, Glyph), String> {
+    let id = number("id")?;
+    let c = char::from_u32(id as u32).ok_or_else(|| format!("{} is not a char", id))?;
+    let (width, height) = (number("width")?.max(0), number("height")?.max(0));
+    let texture = if width > 0 && height > 0 {
+        // fonts with a single page often leave it out
+        let page = number("page").unwrap_or(0) as u32;
+        let page = pages
+            .get(&page)
+            .ok_or_else(|| format!("char {} is on page {}, declared nowhere before", id, page))?;
+        Some(page.sub_texture(
+            number("x")?.max(0) as u32,
+            number("y")?.max(0) as u32,
+            width as u32,
+            height as u32,
+        ))
+    } else {
+        None
+    };
+    let glyph = Glyph {
+        texture,
+        offset: glm::vec2(number("xoffset")? as f32, number("yoffset")? as f32),
+        size: glm::vec2(width as f32, height as f32),
+        advance: number("xadvance")? as f32,
+    };
+    Ok((c, glyph))
+}
+
+/// Splits `tag key=value key="quoted value"` into the tag and its attributes.
+fn split_line(line: &str) -> std::result::Result<(&str, HashMap<&str, &str>), String> {
+    let line = line.trim();
+    let (tag, mut rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let mut attributes = HashMap::new();
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return Ok((tag, attributes));
+        }
+        let (key, after) = rest
+            .split_once('=')
+            .ok_or_else(|| format!("expected `key=value`, got `{}`", rest))?;
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => quoted
+                .split_once('"')
+                .ok_or_else(|| format!("unterminated quote after `{}=`", key))?,
+            None => after.split_once(char::is_whitespace).unwrap_or((after, "")),
+        };
+        attributes.insert(key.trim(), value);
+        rest = after;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, RgbaImage};
+
+    const ARIAL: &str = r#"info face="Arial Black" size=-32 bold=0
+common lineHeight=36 base=29 scaleW=64 scaleH=32 pages=2
+page id=0 file="arial_0.png"
+page id=1 file="arial_1.png"
+chars count=3
+char id=65 x=4 y=8 width=16 height=20 xoffset=1 yoffset=6 xadvance=21 page=1
+char id=66   x=0    y=0    width=12  height=10  xoffset=-1 yoffset=2 xadvance=14
+char id=32 x=0 y=0 width=0 height=0 xoffset=0 yoffset=0 xadvance=9 page=0
+kerning first=65 second=66 amount=-2
+"#;
+
+    fn page(_file: &str) -> Result<Texture> {
+        let image = DynamicImage::ImageRgba8(RgbaImage::new(64, 32));
+        Ok(Texture::new(image, ImgKind::PNG, TextureStorage::Cpu))
+    }
+
+    fn error_line(source: &str) -> usize {
+        match parse(source, "test.fnt", page) {
+            Err(Error::Config { line, .. }) => line,
+            other => panic!("expected a config error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reads_metrics_glyphs_and_kerning() {
+        let mut files = Vec::new();
+        let font = parse(ARIAL, "test.fnt", |file| {
+            files.push(file.to_string());
+            page(file)
+        })
+        .unwrap();
+        assert_eq!(files, ["arial_0.png", "arial_1.png"]);
+        assert_eq!(font.size(), 32.0);
+        assert_eq!(font.line_height(), 36.0);
+        assert_eq!(font.baseline(), 29.0);
+
+        let a = font.glyph('A').unwrap();
+        let texture = a.texture.as_ref().unwrap();
+        assert_eq!((texture.width, texture.height), (16, 20));
+        assert_eq!(
+            texture.region,
+            glm::vec4(4.0 / 64.0, 8.0 / 32.0, 20.0 / 64.0, 28.0 / 32.0)
+        );
+        assert_eq!(a.offset, glm::vec2(1.0, 6.0));
+        assert_eq!(a.advance, 21.0);
+
+        // without `page=`, on the first page
+        let b = font.glyph('B').unwrap();
+        let pages: Vec<u32> = [a, b]
+            .iter()
+            .map(|glyph| glyph.texture.as_ref().unwrap().id())
+            .collect();
+        assert_ne!(pages[0], pages[1]);
+        assert_eq!(b.offset, glm::vec2(-1.0, 2.0));
+
+        let space = font.glyph(' ').unwrap();
+        assert!(space.texture.is_none());
+        assert_eq!(space.advance, 9.0);
+
+        assert_eq!(font.kerning('A', 'B'), -2.0);
+        assert_eq!(font.kerning('B', 'A'), 0.0);
+    }
+
+    #[test]
+    fn errors_point_at_their_line() {
+        let undeclared_page = "page id=0 file=\"a.png\"\nchar id=65 x=0 y=0 width=4 height=4 xoffset=0 yoffset=0 xadvance=4 page=3";
+        assert_eq!(error_line(undeclared_page), 2);
+        assert_eq!(error_line("info size=32\ncommon lineHeight=big base=29"), 2);
+        assert_eq!(error_line("info size=32\n\npage id=0"), 3);
+        assert_eq!(error_line("info face=\"Arial size=32"), 1);
+        assert_eq!(error_line("kerning first=65 amount=-2"), 1);
+    }
+
+    #[test]
+    fn page_errors_are_passed_through() {
+        let failed = parse("page id=0 file=\"missing.png\"", "test.fnt", |file| {
+            Err(Error::Io {
+                path: file.to_string(),
+                source: std::io::ErrorKind::NotFound.into(),
+            })
+        });
+        assert!(matches!(failed, Err(Error::Io { path, .. }) if path == "missing.png"));
+    }
+}
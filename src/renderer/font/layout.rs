@@ -0,0 +1,532 @@
+use super::Font;
+use crate::renderer::color::Color;
+use crate::renderer::texture::Texture;
+use nalgebra_glm as glm;
+
+/// Where lines sit within the text's box.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// A copy of the text drawn under it, offset by `offset` pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Shadow {
+    pub offset: glm::Vec2,
+    pub color: Color,
+}
+
+/// A border of `width` pixels drawn around every glyph.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Outline {
+    pub width: f32,
+    pub color: Color,
+}
+
+/// How `Font::layout` places text, and `Renderer::draw_text` draws it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextStyle {
+    /// Pixel size the font is scaled to.
+    pub size: f32,
+    pub align: Align,
+    /// Width lines are wrapped at, unlimited with `None`.
+    pub max_width: Option<f32>,
+    /// Multiplies the font's line height.
+    pub line_spacing: f32,
+    pub color: Color,
+    /// Whether color tags in the text are read rather than drawn, see `with_markup`.
+    pub markup: bool,
+    pub shadow: Option<Shadow>,
+    pub outline: Option<Outline>,
+}
+
+impl TextStyle {
+    /// Left aligned, unwrapped white text at `size` pixels.
+    pub fn new(size: f32) -> Self {
+        Self {
+            size,
+            align: Align::Left,
+            max_width: None,
+            line_spacing: 1.0,
+            color: Color::WHITE,
+            markup: false,
+            shadow: None,
+            outline: None,
+        }
+    }
+
+    pub fn with_align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// Wraps lines between words to stay within `max_width`, and lines are
+    /// aligned within it. Words wider than the box are broken anywhere.
+    pub fn with_max_width(mut self, max_width: f32) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    /// 1 by default.
+    pub fn with_line_spacing(mut self, line_spacing: f32) -> Self {
+        self.line_spacing = line_spacing;
+        self
+    }
+
+    /// White by default; its alpha fades markup colors too.
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Reads color tags: `[red]HP[/] 10` draws "HP" in red. Tags take any
+    /// name of `Color::from_name`, nest, and `[/]` goes back to the previous
+    /// color. `[[` draws a `[`, and unknown tags are drawn as they are.
+    pub fn with_markup(mut self) -> Self {
+        self.markup = true;
+        self
+    }
+
+    pub fn with_shadow(mut self, offset: glm::Vec2, color: Color) -> Self {
+        self.shadow = Some(Shadow { offset, color });
+        self
+    }
+
+    pub fn with_outline(mut self, width: f32, color: Color) -> Self {
+        self.outline = Some(Outline { width, color });
+        self
+    }
+}
+
+/// A glyph placed by `Font::layout`, relative to the top left corner of the
+/// text's box.
+#[derive(Clone, Debug)]
+pub struct PlacedGlyph {
+    pub c: char,
+    pub texture: Texture,
+    pub position: glm::Vec2,
+    pub size: glm::Vec2,
+    pub color: Color,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TextLayout {
+    pub glyphs: Vec<PlacedGlyph>,
+    /// Width of the widest line and height of all the lines.
+    pub size: glm::Vec2,
+    pub lines: usize,
+}
+
+/// A char of the text once markup is taken out.
+#[derive(Clone, Copy)]
+struct Styled {
+    c: char,
+    color: Color,
+}
+
+pub(crate) fn layout(font: &Font, text: &str, style: &TextStyle) -> TextLayout {
+    let scale = if font.size > 0.0 {
+        style.size / font.size
+    } else {
+        1.0
+    };
+    let line_height = font.line_height * scale * style.line_spacing;
+
+    let text = styled(text, style);
+    let mut lines = Vec::new();
+    for paragraph in text.split(|styled| styled.c == '\n') {
+        let paragraph = match paragraph.split_last() {
+            Some((last, rest)) if last.c == '\r' => rest,
+            _ => paragraph,
+        };
+        match style.max_width {
+            Some(max_width) => lines.extend(wrap(font, paragraph, scale, max_width)),
+            None => lines.push(paragraph),
+        }
+    }
+
+    let widths: Vec<f32> = lines
+        .iter()
+        .map(|line| advance(font, trim_end(line), scale))
+        .collect();
+    let widest = widths.iter().copied().fold(0.0, f32::max);
+    let box_width = style.max_width.unwrap_or(widest);
+    let mut glyphs = Vec::new();
+    for (i, (line, line_width)) in lines.iter().zip(&widths).enumerate() {
+        let mut pen = match style.align {
+            Align::Left => 0.0,
+            Align::Center => (box_width - line_width) / 2.0,
+            Align::Right => box_width - line_width,
+        };
+        let top = i as f32 * line_height;
+        let mut previous = None;
+        for &Styled { c, color } in line.iter() {
+            let Some(glyph) = font.glyph_or_fallback(c) else {
+                continue;
+            };
+            if let Some(previous) = previous {
+                pen += font.kerning(previous, c) * scale;
+            }
+            if let Some(texture) = &glyph.texture {
+                glyphs.push(PlacedGlyph {
+                    c,
+                    texture: texture.clone(),
+                    position: glm::vec2(pen, top) + glyph.offset * scale,
+                    size: glyph.size * scale,
+                    color,
+                });
+            }
+            pen += glyph.advance * scale;
+            previous = Some(c);
+        }
+    }
+
+    TextLayout {
+        glyphs,
+        size: glm::vec2(widest, lines.len() as f32 * line_height),
+        lines: lines.len(),
+    }
+}
+
+/// The chars of `text` with their color, markup read if the style asks for it.
+fn styled(text: &str, style: &TextStyle) -> Vec<Styled> {
+    let plain = |c| Styled {
+        c,
+        color: style.color,
+    };
+    if !style.markup {
+        return text.chars().map(plain).collect();
+    }
+    let mut chars = Vec::with_capacity(text.len());
+    let mut colors = vec![style.color];
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        if c == '[' {
+            if let Some(after) = rest.strip_prefix('[') {
+                rest = after;
+            } else if let Some((tag, after)) = rest.split_once(']') {
+                let color =
+                    Color::from_name(tag).map(|color| color.with_alpha(color.a * style.color.a));
+                match (tag, color) {
+                    ("/", _) => {
+                        if colors.len() > 1 {
+                            colors.pop();
+                        }
+                        rest = after;
+                        continue;
+                    }
+                    (_, Some(color)) => {
+                        colors.push(color);
+                        rest = after;
+                        continue;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        chars.push(Styled {
+            c,
+            color: *colors.last().unwrap(),
+        });
+    }
+    chars
+}
+
+fn trim_end(line: &[Styled]) -> &[Styled] {
+    let end = line
+        .iter()
+        .rposition(|styled| styled.c != ' ')
+        .map_or(0, |last| last + 1);
+    &line[..end]
+}
+
+fn trim_start(line: &[Styled]) -> &[Styled] {
+    let start = line
+        .iter()
+        .position(|styled| styled.c != ' ')
+        .unwrap_or(line.len());
+    &line[start..]
+}
+
+fn advance(font: &Font, line: &[Styled], scale: f32) -> f32 {
+    let mut pen = 0.0;
+    let mut previous = None;
+    for styled in line {
+        let Some(glyph) = font.glyph_or_fallback(styled.c) else {
+            continue;
+        };
+        if let Some(previous) = previous {
+            pen += font.kerning(previous, styled.c) * scale;
+        }
+        pen += glyph.advance * scale;
+        previous = Some(styled.c);
+    }
+    pen
+}
+
+/// Greedily splits `paragraph` into lines no wider than `max_width`, between
+/// words when it can. Every line holds at least one char, so a box narrower
+/// than a glyph still terminates.
+fn wrap<'t>(font: &Font, paragraph: &'t [Styled], scale: f32, max_width: f32) -> Vec<&'t [Styled]> {
+    let mut lines = Vec::new();
+    let mut rest = paragraph;
+    loop {
+        let mut pen = 0.0;
+        let mut previous = None;
+        let mut last_space = None;
+        let mut overflow = None;
+        for (i, styled) in rest.iter().enumerate() {
+            let c = styled.c;
+            let Some(glyph) = font.glyph_or_fallback(c) else {
+                continue;
+            };
+            let kerning = previous.map_or(0.0, |previous| font.kerning(previous, c));
+            let next = pen + (kerning + glyph.advance) * scale;
+            // spaces may hang past the edge, they are not drawn at the end of a line
+            if c != ' ' && next > max_width && i > 0 {
+                overflow = Some(i);
+                break;
+            }
+            if c == ' ' {
+                last_space = Some(i);
+            }
+            pen = next;
+            previous = Some(c);
+        }
+        let Some(overflow) = overflow else {
+            lines.push(rest);
+            return lines;
+        };
+        let (line, next) = match last_space {
+            Some(space) if trim_end(&rest[..space]).is_empty() => rest.split_at(overflow),
+            Some(space) => rest.split_at(space),
+            None => rest.split_at(overflow),
+        };
+        lines.push(trim_end(line));
+        rest = trim_start(next);
+        if rest.is_empty() {
+            return lines;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::font::Glyph;
+    use crate::renderer::testing::pixel;
+
+    /// Printable ASCII 10 pixels wide at size 10, on 12 pixel lines, where
+    /// 'A' 'V' kerns by -2 and the space draws nothing.
+    fn font() -> Font {
+        let pixel = pixel();
+        let mut font = Font::new(10.0, 12.0, 8.0);
+        for c in ' '..='~' {
+            let glyph = Glyph {
+                texture: (c != ' ').then(|| pixel.clone()),
+                offset: glm::Vec2::zeros(),
+                size: glm::vec2(10.0, 10.0),
+                advance: 10.0,
+            };
+            font.insert_glyph(c, glyph);
+        }
+        font.insert_kerning('A', 'V', -2.0);
+        font
+    }
+
+    /// The drawn chars of each line, spaces left out, with the x of the first.
+    fn lines(layout: &TextLayout) -> Vec<(String, f32)> {
+        let mut lines: Vec<(String, f32)> = vec![(String::new(), 0.0); layout.lines];
+        for glyph in &layout.glyphs {
+            let (line, start) = &mut lines[(glyph.position.y / 12.0) as usize];
+            if line.is_empty() {
+                *start = glyph.position.x;
+            }
+            line.push(glyph.c);
+        }
+        lines
+    }
+
+    #[test]
+    fn lines_wrap_between_words_and_break_long_ones() {
+        let cases: [(&str, f32, &[&str]); 7] = [
+            ("ab cd ef", 50.0, &["abcd", "ef"]),
+            ("ab cd ef", 40.0, &["ab", "cd", "ef"]),
+            // the spaces hang past the edge rather than start the next line
+            ("ab   cd", 20.0, &["ab", "cd"]),
+            ("abcdefgh", 30.0, &["abc", "def", "gh"]),
+            ("a abcdef", 30.0, &["a", "abc", "def"]),
+            // a glyph wider than the box still gets a line
+            ("ab", 5.0, &["a", "b"]),
+            ("ab\ncd ef", 40.0, &["ab", "cd", "ef"]),
+        ];
+        let font = font();
+        for (text, max_width, expected) in cases {
+            let layout = font.layout(text, &TextStyle::new(10.0).with_max_width(max_width));
+            let lines: Vec<String> = lines(&layout).into_iter().map(|(line, _)| line).collect();
+            assert_eq!(lines, expected, "{:?} at {}", text, max_width);
+        }
+    }
+
+    #[test]
+    fn newlines_start_lines_even_empty_ones() {
+        let font = font();
+        let style = TextStyle::new(10.0);
+        let layout = font.layout("ab\ncd\r\n\nef", &style);
+        let lines: Vec<String> = lines(&layout).into_iter().map(|(line, _)| line).collect();
+        assert_eq!(lines, ["ab", "cd", "", "ef"]);
+        assert_eq!(layout.size, glm::vec2(20.0, 48.0));
+        assert_eq!(font.measure("", &style), glm::vec2(0.0, 12.0));
+    }
+
+    #[test]
+    fn lines_are_aligned_within_the_box() {
+        let cases = [
+            (Align::Left, None, [0.0, 0.0]),
+            (Align::Center, None, [10.0, 0.0]),
+            (Align::Right, None, [20.0, 0.0]),
+            (Align::Left, Some(60.0), [0.0, 0.0]),
+            (Align::Center, Some(60.0), [20.0, 10.0]),
+            (Align::Right, Some(60.0), [40.0, 20.0]),
+        ];
+        let font = font();
+        // trailing spaces do not count towards the width
+        for (align, max_width, expected) in cases {
+            let mut style = TextStyle::new(10.0).with_align(align);
+            style.max_width = max_width;
+            let starts: Vec<f32> = lines(&font.layout("ab  \nabcd", &style))
+                .into_iter()
+                .map(|(_, start)| start)
+                .collect();
+            assert_eq!(starts, expected, "{:?} in {:?}", align, max_width);
+        }
+    }
+
+    #[test]
+    fn kerning_moves_the_pen_between_pairs() {
+        let font = font();
+        let xs = |text, size| -> Vec<f32> {
+            let layout = font.layout(text, &TextStyle::new(size));
+            layout.glyphs.iter().map(|glyph| glyph.position.x).collect()
+        };
+        assert_eq!(xs("AVA", 10.0), [0.0, 8.0, 18.0]);
+        assert_eq!(xs("VAV", 10.0), [0.0, 10.0, 18.0]);
+        // at twice the font's size
+        assert_eq!(xs("AV", 20.0), [0.0, 16.0]);
+        assert_eq!(font.measure("AVA", &TextStyle::new(10.0)).x, 28.0);
+        // the pair shortens the line, so it still fits
+        let fits = TextStyle::new(10.0).with_max_width(28.0);
+        assert_eq!(font.layout("AVA", &fits).lines, 1);
+    }
+
+    #[test]
+    fn missing_chars_take_the_fallback() {
+        let font = font();
+        let layout = font.layout("a\u{e9}b", &TextStyle::new(10.0));
+        let xs: Vec<(char, f32)> = layout
+            .glyphs
+            .iter()
+            .map(|glyph| (glyph.c, glyph.position.x))
+            .collect();
+        assert_eq!(xs, [('a', 0.0), ('\u{e9}', 10.0), ('b', 20.0)]);
+    }
+
+    /// The drawn chars of `text` with their colors.
+    fn colors(text: &str, style: &TextStyle) -> Vec<(char, Color)> {
+        font()
+            .layout(text, style)
+            .glyphs
+            .iter()
+            .map(|glyph| (glyph.c, glyph.color))
+            .collect()
+    }
+
+    #[test]
+    fn markup_tags_color_the_text_between_them() {
+        let (w, r, b) = (Color::WHITE, Color::RED, Color::BLUE);
+        let cases: [(&str, &[(char, Color)]); 9] = [
+            ("[red]a[/]b", &[('a', r), ('b', w)]),
+            (
+                "[red]a[blue]b[/]c[/]d",
+                &[('a', r), ('b', b), ('c', r), ('d', w)],
+            ),
+            // open to the end of the text
+            ("a[RED]b", &[('a', w), ('b', r)]),
+            ("[#0000ff]a", &[('a', b)]),
+            // closing more than was opened keeps the style's color
+            ("[/]a[/]", &[('a', w)]),
+            (
+                "[[red]a",
+                &[('[', w), ('r', w), ('e', w), ('d', w), (']', w), ('a', w)],
+            ),
+            (
+                "[nope]a",
+                &[
+                    ('[', w),
+                    ('n', w),
+                    ('o', w),
+                    ('p', w),
+                    ('e', w),
+                    (']', w),
+                    ('a', w),
+                ],
+            ),
+            ("a[red", &[('a', w), ('[', w), ('r', w), ('e', w), ('d', w)]),
+            ("[]a", &[('[', w), (']', w), ('a', w)]),
+        ];
+        let style = TextStyle::new(10.0).with_markup();
+        for (text, expected) in cases {
+            assert_eq!(colors(text, &style), expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn markup_is_drawn_as_is_unless_asked_for() {
+        let plain = colors("[red]a", &TextStyle::new(10.0));
+        let chars: String = plain.iter().map(|(c, _)| c).collect();
+        assert_eq!(chars, "[red]a");
+        assert!(plain.iter().all(|(_, color)| *color == Color::WHITE));
+    }
+
+    #[test]
+    fn markup_colors_fade_with_the_style() {
+        let style = TextStyle::new(10.0)
+            .with_color(Color::WHITE.with_alpha(0.5))
+            .with_markup();
+        assert_eq!(
+            colors("a[red]b", &style),
+            [
+                ('a', Color::rgba(1.0, 1.0, 1.0, 0.5)),
+                ('b', Color::rgba(1.0, 0.0, 0.0, 0.5))
+            ]
+        );
+    }
+
+    #[test]
+    fn tags_take_no_room_and_carry_across_wraps() {
+        let font = font();
+        let style = TextStyle::new(10.0).with_max_width(30.0).with_markup();
+        assert_eq!(font.layout("[red]abc[/]", &style).lines, 1);
+
+        let layout = font.layout("x [red]ab cd[/] ef", &style);
+        let lines: Vec<String> = lines(&layout).into_iter().map(|(line, _)| line).collect();
+        assert_eq!(lines, ["x", "ab", "cd", "ef"]);
+        let (w, r) = (Color::WHITE, Color::RED);
+        assert_eq!(
+            colors("x [red]ab cd[/] ef", &style),
+            [
+                ('x', w),
+                ('a', r),
+                ('b', r),
+                ('c', r),
+                ('d', r),
+                ('e', w),
+                ('f', w)
+            ]
+        );
+    }
+}
@@ -0,0 +1,435 @@
+pub mod bmfont;
+pub mod layout;
+
+pub use layout::{Align, Outline, PlacedGlyph, Shadow, TextLayout, TextStyle};
+
+use crate::error::{Error, Result};
+use crate::renderer::atlas::AtlasBuilder;
+use crate::renderer::texture::{SpriteSheet, Texture, TextureStorage};
+use ab_glyph::{Font as _, FontRef, PxScale, ScaleFont};
+use image::{DynamicImage, Rgba, RgbaImage};
+use nalgebra_glm as glm;
+use std::collections::HashMap;
+use std::fs;
+
+/// Drawn in place of characters the font has no glyph for.
+const FALLBACK: char = '?';
+
+/// A character of a font, in pixels at the font's own size.
+#[derive(Clone, Debug)]
+pub struct Glyph {
+    /// `None` for glyphs that draw nothing, like the space.
+    pub texture: Option<Texture>,
+    /// Top left corner of the texture relative to the pen, which sits at the
+    /// top of the line.
+    pub offset: glm::Vec2,
+    pub size: glm::Vec2,
+    /// How far the pen moves after the glyph.
+    pub advance: f32,
+}
+
+/// Glyphs with their metrics and kerning, loaded from a BMFont, rasterized
+/// from a TrueType font or cut from a grid sprite sheet. Text is laid out
+/// by `layout` and drawn by `Renderer::draw_text`, at any size: glyphs are
+/// scaled from the size the font was made at.
+#[derive(Clone, Debug)]
+pub struct Font {
+    size: f32,
+    line_height: f32,
+    baseline: f32,
+    glyphs: HashMap<char, Glyph>,
+    kerning: HashMap<(char, char), f32>,
+}
+
+impl Font {
+    pub(crate) fn new(size: f32, line_height: f32, baseline: f32) -> Self {
+        Self {
+            size,
+            line_height,
+            baseline,
+            glyphs: HashMap::new(),
+            kerning: HashMap::new(),
+        }
+    }
+
+    /// Monospace font from a sheet of square cells where sprite `i` is the
+    /// glyph of char `i`, like `terminal8x8.png`.
+    pub fn from_grid(sheet: &SpriteSheet) -> Self {
+        let cell = sheet.get(0).map_or(glm::vec2(1.0, 1.0), |sprite| {
+            glm::vec2(sprite.width as f32, sprite.height as f32)
+        });
+        let mut font = Self::new(cell.y, cell.y, cell.y);
+        for i in 0..sheet.len() {
+            if let Some(c) = char::from_u32(i as u32) {
+                font.glyphs.insert(
+                    c,
+                    Glyph {
+                        texture: Some(sheet[i].clone()),
+                        offset: glm::Vec2::zeros(),
+                        size: cell,
+                        advance: cell.x,
+                    },
+                );
+            }
+        }
+        font
+    }
+
+    /// Reads a text BMFont `.fnt`, with its pages next to it uploaded to GL.
+    pub fn load_bmfont(path: &str) -> Result<Self> {
+        bmfont::load(path, TextureStorage::Gpu)
+    }
+
+    /// Pixel size the metrics are given in.
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+
+    /// Distance between two lines at the font's size.
+    pub fn line_height(&self) -> f32 {
+        self.line_height
+    }
+
+    /// Distance from the top of a line to its baseline at the font's size.
+    pub fn baseline(&self) -> f32 {
+        self.baseline
+    }
+
+    pub fn glyph(&self, c: char) -> Option<&Glyph> {
+        self.glyphs.get(&c)
+    }
+
+    /// Adjustment of the pen between `left` and `right`, at the font's size.
+    pub fn kerning(&self, left: char, right: char) -> f32 {
+        self.kerning.get(&(left, right)).copied().unwrap_or(0.0)
+    }
+
+    /// Places the glyphs of `text` relative to the top left corner of its box.
+    pub fn layout(&self, text: &str, style: &TextStyle) -> TextLayout {
+        layout::layout(self, text, style)
+    }
+
+    /// Size of `text` as `layout` would place it, e.g. to center a button's label.
+    pub fn measure(&self, text: &str, style: &TextStyle) -> glm::Vec2 {
+        self.layout(text, style).size
+    }
+
+    pub(crate) fn insert_glyph(&mut self, c: char, glyph: Glyph) {
+        self.glyphs.insert(c, glyph);
+    }
+
+    pub(crate) fn insert_kerning(&mut self, left: char, right: char, amount: f32) {
+        self.kerning.insert((left, right), amount);
+    }
+
+    /// The glyph drawn for `c`: its own, or the fallback's. Missing both, `c`
+    /// takes no room.
+    fn glyph_or_fallback(&self, c: char) -> Option<&Glyph> {
+        self.glyphs.get(&c).or_else(|| self.glyphs.get(&FALLBACK))
+    }
+}
+
+/// Rasterizes a TrueType or OpenType font at `pixel_size` into an atlas, with
+/// ab_glyph. Printable ASCII and Latin-1 are rasterized; other characters
+/// must be asked for.
+pub struct TtfFontBuilder {
+    path: String,
+    pixel_size: f32,
+    chars: Vec<char>,
+    storage: TextureStorage,
+}
+
+impl TtfFontBuilder {
+    pub fn init(path: &str, pixel_size: f32) -> Self {
+        Self {
+            path: path.to_string(),
+            pixel_size,
+            chars: (' '..='~').chain('\u{a1}'..='\u{ff}').collect(),
+            storage: TextureStorage::Gpu,
+        }
+    }
+
+    /// Also rasterizes `chars`, e.g. the letters of a translation.
+    pub fn with_chars(mut self, chars: &str) -> Self {
+        for c in chars.chars() {
+            if !c.is_control() && !self.chars.contains(&c) {
+                self.chars.push(c);
+            }
+        }
+        self
+    }
+
+    /// Where the glyph atlas is created.
+    pub fn with_storage(mut self, storage: TextureStorage) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    pub fn build(self) -> Font {
+        self.try_build().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_build(self) -> Result<Font> {
+        let data = fs::read(&self.path).map_err(|source| Error::Io {
+            path: self.path.clone(),
+            source,
+        })?;
+        self.rasterize(&data)
+    }
+
+    /// Builds the font from the contents of its file.
+    fn rasterize(&self, data: &[u8]) -> Result<Font> {
+        let font_error = |message: String| Error::Font {
+            path: self.path.clone(),
+            message,
+        };
+        let ttf = FontRef::try_from_slice(data).map_err(|e| font_error(e.to_string()))?;
+        // ab_glyph scales the height from ascent to descent to the pixel size,
+        // the font is asked for at an em of `pixel_size`
+        let units_per_em = ttf.units_per_em().unwrap_or(ttf.height_unscaled());
+        let scale = PxScale::from(self.pixel_size * ttf.height_unscaled() / units_per_em);
+        let scaled = ttf.as_scaled(scale);
+        let mut font = Font::new(
+            self.pixel_size,
+            scaled.height() + scaled.line_gap(),
+            scaled.ascent(),
+        );
+
+        // a glyph wider than a row of the atlas is a corrupt one, stopped
+        // before its bitmap is allocated
+        let atlas_width = (self.pixel_size * 16.0).max(256.0) as u32;
+        let mut atlas = AtlasBuilder::init(atlas_width).with_storage(self.storage);
+        let mut drawn = Vec::new();
+        let mut present = Vec::new();
+        for &c in &self.chars {
+            let id = ttf.glyph_id(c);
+            if id.0 == 0 {
+                continue;
+            }
+            present.push((c, id));
+            let mut glyph = Glyph {
+                texture: None,
+                offset: glm::Vec2::zeros(),
+                size: glm::Vec2::zeros(),
+                advance: scaled.h_advance(id),
+            };
+            if let Some(outlined) = scaled.outline_glyph(id.with_scale(scale)) {
+                let bounds = outlined.px_bounds();
+                let (width, height) = (bounds.width() as u32, bounds.height() as u32);
+                if width > atlas_width || height > atlas_width {
+                    return Err(font_error(format!(
+                        "the glyph of {:?} is {}x{} pixels, larger than its atlas",
+                        c, width, height
+                    )));
+                }
+                if width > 0 && height > 0 {
+                    let mut pixels = RgbaImage::from_pixel(width, height, Rgba([255, 255, 255, 0]));
+                    outlined.draw(|x, y, coverage| {
+                        pixels.get_pixel_mut(x, y)[3] = (coverage.min(1.0) * 255.0).round() as u8;
+                    });
+                    atlas = atlas.with_pixels(&glyph_name(c), DynamicImage::ImageRgba8(pixels));
+                    // bounds are around the pen on the baseline
+                    glyph.offset = glm::vec2(bounds.min.x, bounds.min.y + scaled.ascent());
+                    glyph.size = glm::vec2(width as f32, height as f32);
+                    drawn.push(c);
+                }
+            }
+            font.insert_glyph(c, glyph);
+        }
+
+        let atlas = atlas.build();
+        for c in drawn {
+            if let Some(glyph) = font.glyphs.get_mut(&c) {
+                glyph.texture = atlas.get(&glyph_name(c));
+            }
+        }
+        for &(left, left_id) in &present {
+            for &(right, right_id) in &present {
+                let amount = scaled.kern(left_id, right_id);
+                if amount != 0.0 {
+                    font.insert_kerning(left, right, amount);
+                }
+            }
+        }
+        Ok(font)
+    }
+
+    /// Fonts are cached by file and size.
+    pub(crate) fn key(&self) -> String {
+        format!("{}@{}", self.path, self.pixel_size)
+    }
+}
+
+fn glyph_name(c: char) -> String {
+    format!("U+{:04X}", c as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Outline of a glyph, (x min, y min, x max, y max) in font units.
+    type Square = (i16, i16, i16, i16);
+
+    /// A TrueType file with an 800 unit ascent, a 200 unit descent and 500
+    /// unit advances: `glyphs` are drawn for 'A', 'V' and ' ' in turn, a
+    /// `None` one draws nothing. 'A' 'V' kerns by -100.
+    fn ttf(units_per_em: u16, glyphs: [Option<Square>; 3]) -> Vec<u8> {
+        let mut head = vec![0; 54];
+        head[18..20].copy_from_slice(&units_per_em.to_be_bytes());
+        let mut hhea = vec![0; 36];
+        hhea[4..6].copy_from_slice(&800i16.to_be_bytes());
+        hhea[6..8].copy_from_slice(&(-200i16).to_be_bytes());
+        hhea[34..36].copy_from_slice(&4u16.to_be_bytes());
+        let mut maxp = vec![0, 0, 0x50, 0];
+        maxp.extend(4u16.to_be_bytes());
+
+        let mut cmap = vec![0, 0, 0, 1, 0, 3, 0, 10, 0, 0, 0, 12, 0, 12];
+        cmap.extend([0; 10]);
+        cmap.extend(3u32.to_be_bytes());
+        for (c, glyph) in [(' ', 3u32), ('A', 1), ('V', 2)] {
+            cmap.extend((c as u32).to_be_bytes());
+            cmap.extend((c as u32).to_be_bytes());
+            cmap.extend(glyph.to_be_bytes());
+        }
+
+        // the missing glyph is empty, the others one on-curve square each
+        let mut glyf = Vec::new();
+        let mut loca = vec![0; 4];
+        for square in glyphs {
+            if let Some((x_min, y_min, x_max, y_max)) = square {
+                let values = [1, x_min, y_min, x_max, y_max];
+                glyf.extend(values.iter().flat_map(|value| value.to_be_bytes()));
+                glyf.extend([0, 3, 0, 0, 1, 1, 1, 1]);
+                for deltas in [
+                    [x_min, 0, x_max - x_min, 0],
+                    [y_min, y_max - y_min, 0, y_min - y_max],
+                ] {
+                    glyf.extend(deltas.iter().flat_map(|delta| delta.to_be_bytes()));
+                }
+            }
+            loca.extend((glyf.len() as u16 / 2).to_be_bytes());
+        }
+        let hmtx = [500u16, 0].repeat(4);
+        let hmtx: Vec<u8> = hmtx.iter().flat_map(|value| value.to_be_bytes()).collect();
+        let mut kern = vec![0, 0, 0, 1, 0, 0, 0, 20, 0, 1, 0, 1, 0, 0, 0, 0, 0, 0];
+        kern.extend([0, 1, 0, 2]);
+        kern.extend((-100i16).to_be_bytes());
+
+        let tables = [
+            (b"cmap", cmap),
+            (b"glyf", glyf),
+            (b"head", head),
+            (b"hhea", hhea),
+            (b"hmtx", hmtx),
+            (b"kern", kern),
+            (b"loca", loca),
+            (b"maxp", maxp),
+        ];
+        let mut data = vec![0, 1, 0, 0];
+        data.extend((tables.len() as u16).to_be_bytes());
+        data.extend([0; 6]);
+        let mut offset = 12 + 16 * tables.len();
+        for (tag, table) in &tables {
+            data.extend(*tag);
+            data.extend([0; 4]);
+            data.extend((offset as u32).to_be_bytes());
+            data.extend((table.len() as u32).to_be_bytes());
+            offset += table.len();
+        }
+        for (_, table) in tables {
+            data.extend(table);
+        }
+        data
+    }
+
+    fn squares() -> [Option<Square>; 3] {
+        [Some((100, 0, 400, 700)), Some((0, -200, 500, 800)), None]
+    }
+
+    fn builder() -> TtfFontBuilder {
+        TtfFontBuilder::init("test.ttf", 20.0).with_storage(TextureStorage::Cpu)
+    }
+
+    #[test]
+    fn truetype_glyphs_are_placed_from_the_top_of_the_line() {
+        let font = builder().rasterize(&ttf(1000, squares())).unwrap();
+        assert_eq!(font.size(), 20.0);
+        assert_eq!(font.line_height(), 20.0);
+        assert_eq!(font.baseline(), 16.0);
+
+        // 100..400 x 0..700 units, at 20 pixels per 1000
+        let a = font.glyph('A').unwrap();
+        assert_eq!(a.offset, glm::vec2(2.0, 2.0));
+        assert_eq!(a.size, glm::vec2(6.0, 14.0));
+        assert_eq!(a.advance, 10.0);
+        let texture = a.texture.as_ref().unwrap();
+        let atlas = texture.pixels().unwrap();
+        let (x, y) = (
+            texture.region.x * atlas.width() as f32,
+            texture.region.y * atlas.height() as f32,
+        );
+        let pixels = image::imageops::crop_imm(atlas, x as u32, y as u32, 6, 14).to_image();
+        assert!(pixels.pixels().all(|pixel| *pixel == Rgba([255; 4])));
+
+        // the em box, from the ascent to the descent
+        let v = font.glyph('V').unwrap();
+        assert_eq!(
+            (v.offset, v.size),
+            (glm::vec2(0.0, 0.0), glm::vec2(10.0, 20.0))
+        );
+
+        let space = font.glyph(' ').unwrap();
+        assert!(space.texture.is_none());
+        assert_eq!(space.advance, 10.0);
+        // not in the font, so drawn as the fallback if at all
+        assert!(font.glyph('B').is_none());
+
+        assert_eq!(font.kerning('A', 'V'), -2.0);
+        assert_eq!(font.kerning('V', 'A'), 0.0);
+    }
+
+    #[test]
+    fn oversized_glyphs_are_errors() {
+        let huge = [Some((0, 0, 32767, 32767)), None, None];
+        let built = builder().rasterize(&ttf(16, huge));
+        assert!(
+            matches!(&built, Err(Error::Font { message, .. }) if message.contains("larger than")),
+            "{:?}",
+            built.map(|_| ())
+        );
+    }
+
+    #[test]
+    fn corrupt_files_are_errors_not_panics() {
+        let data = ttf(1000, squares());
+        for length in 0..data.len() {
+            let _ = builder().rasterize(&data[..length]);
+        }
+        assert!(builder().rasterize(&data[..100]).is_err());
+        assert!(builder().rasterize(&ttf(0, squares())).is_err());
+
+        // the same bytes flipped at random places, every run
+        let mut seed = 0x2545_f491_u32;
+        let mut random = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed as usize
+        };
+        for _ in 0..2000 {
+            let mut corrupt = data.clone();
+            for _ in 0..1 + random() % 4 {
+                let at = random() % corrupt.len();
+                corrupt[at] = random() as u8;
+            }
+            let _ = builder().rasterize(&corrupt);
+        }
+    }
+
+    #[test]
+    fn missing_files_are_io_errors() {
+        let built = TtfFontBuilder::init("resources/fonts/missing.ttf", 20.0).try_build();
+        assert!(matches!(built, Err(Error::Io { .. })));
+    }
+}
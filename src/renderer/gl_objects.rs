@@ -2,28 +2,95 @@ use gl::types::*;
 use std::mem;
 use std::os::raw::c_void;
 use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Kinds of GL objects counted for the leak report.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum GlKind {
+    Texture,
+    Buffer,
+    VertexArray,
+    Program,
+}
+
+static LIVE_OBJECTS: [AtomicUsize; 4] = [
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+];
+
+impl GlKind {
+    const ALL: [GlKind; 4] = [
+        GlKind::Texture,
+        GlKind::Buffer,
+        GlKind::VertexArray,
+        GlKind::Program,
+    ];
+
+    pub(crate) fn created(self) {
+        LIVE_OBJECTS[self as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn deleted(self) {
+        LIVE_OBJECTS[self as usize].fetch_sub(1, Ordering::Relaxed);
+    }
+
+    fn live(self) -> usize {
+        LIVE_OBJECTS[self as usize].load(Ordering::Relaxed)
+    }
+}
+
+/// Logs the GL objects still alive. Meant to be called once everything
+/// holding GL resources has been dropped; only reports in debug builds.
+pub(crate) fn report_live_objects() {
+    if !cfg!(debug_assertions) {
+        return;
+    }
+    for kind in GlKind::ALL {
+        if kind.live() > 0 {
+            log::warn!("{} {:?} object(s) leaked at shutdown", kind.live(), kind);
+        }
+    }
+}
 
 pub struct VBO {
     pub id: u32,
     pub count: usize,
 }
 impl VBO {
-    pub fn new(vertices: &[f32]) -> Self {
+    /// Allocates room for `capacity` floats, to be filled later with `update`.
+    pub fn dynamic(capacity: usize) -> Self {
         let mut id = 0;
         unsafe {
             gl::GenBuffers(1, &mut id);
             gl::BindBuffer(gl::ARRAY_BUFFER, id);
             gl::BufferData(
                 gl::ARRAY_BUFFER,
-                (vertices.len() * mem::size_of::<GLfloat>()) as GLsizeiptr,
-                &vertices[0] as *const f32 as *const c_void,
-                gl::STATIC_DRAW,
+                (capacity * mem::size_of::<GLfloat>()) as GLsizeiptr,
+                ptr::null(),
+                gl::DYNAMIC_DRAW,
             );
         }
-        log::debug!("Create new VBO id: {}", id);
+        GlKind::Buffer.created();
+        log::debug!("Create new dynamic VBO id: {}", id);
         Self {
             id,
-            count: vertices.len(),
+            count: capacity,
+        }
+    }
+
+    /// Overwrites the start of the buffer with `vertices`.
+    pub fn update(&self, vertices: &[f32]) {
+        debug_assert!(vertices.len() <= self.count);
+        self.bind();
+        unsafe {
+            gl::BufferSubData(
+                gl::ARRAY_BUFFER,
+                0,
+                mem::size_of_val(vertices) as GLsizeiptr,
+                vertices.as_ptr() as *const c_void,
+            );
         }
     }
 
@@ -37,11 +104,14 @@ impl VBO {
             gl::BindBuffer(gl::ARRAY_BUFFER, 0);
         }
     }
+}
 
-    pub fn delete(&mut self) {
+impl Drop for VBO {
+    fn drop(&mut self) {
         unsafe {
-            gl::DeleteBuffers(1, &mut self.id);
+            gl::DeleteBuffers(1, &self.id);
         }
+        GlKind::Buffer.deleted();
     }
 }
 
@@ -63,6 +133,7 @@ impl EBO {
                 gl::STATIC_DRAW,
             );
         }
+        GlKind::Buffer.created();
         log::debug!("Create new EBO id: {}", id);
         Self { id }
     }
@@ -77,11 +148,14 @@ impl EBO {
             gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, 0);
         }
     }
+}
 
-    pub fn delete(&mut self) {
+impl Drop for EBO {
+    fn drop(&mut self) {
         unsafe {
-            gl::DeleteBuffers(1, &mut self.id);
+            gl::DeleteBuffers(1, &self.id);
         }
+        GlKind::Buffer.deleted();
     }
 }
 
@@ -96,6 +170,7 @@ impl VAO {
         unsafe {
             gl::GenVertexArrays(1, &mut id);
         }
+        GlKind::VertexArray.created();
         log::debug!("Create new VAO id: {}", id);
         Self { id }
     }
@@ -139,9 +214,12 @@ impl VAO {
             gl::BindVertexArray(0);
         }
     }
+}
 
-    pub fn delete(&mut self) {
-        unsafe { gl::DeleteVertexArrays(1, &mut self.id) }
+impl Drop for VAO {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteVertexArrays(1, &self.id) }
+        GlKind::VertexArray.deleted();
     }
 }
 
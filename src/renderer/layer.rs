@@ -0,0 +1,25 @@
+/// Render layers, drawn back to front in this order whatever the order of
+/// the draw calls. Within a layer, sprites are sorted by depth, and sprites
+/// at the same depth are drawn in call order.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Layer {
+    /// Skies and backdrops, often scrolled with parallax.
+    Background,
+    Tiles,
+    #[default]
+    Entities,
+    /// Drawn over the entities, e.g. foliage they walk behind.
+    Foreground,
+    /// HUD and menus, over everything.
+    Ui,
+}
+
+impl Layer {
+    pub const ALL: [Layer; 5] = [
+        Layer::Background,
+        Layer::Tiles,
+        Layer::Entities,
+        Layer::Foreground,
+        Layer::Ui,
+    ];
+}
@@ -1,15 +1,39 @@
+pub mod assets;
+pub mod atlas;
+pub mod backends;
+pub mod camera;
+pub mod color;
+pub mod draw_params;
+pub mod font;
 mod gl_objects;
-mod shader;
+pub mod layer;
+pub mod shader;
+mod shapes;
+#[cfg(test)]
+mod testing;
 pub mod texture;
 pub mod utils;
+pub mod viewport;
 
 use self::utils::ResourcesManager;
-use gl::types::*;
-use gl_objects::{MyTypes, VertexBufferElement, VertexesLayout, EBO, VAO, VBO};
+use crate::error::Result;
+use assets::Handle;
+use backends::common::{DrawCommand, RenderBackend, RenderStats};
+use backends::gl_backend::{GlBackend, SPRITE_SHADER};
+use backends::recording_backend::RecordingBackend;
+use camera::{Camera2D, Space};
+use color::Color;
+use draw_params::DrawParams;
+use font::{Font, TextStyle};
+use image::{DynamicImage, Rgba, RgbaImage};
+use layer::Layer;
 use nalgebra_glm as glm;
-use std::mem;
-use std::ptr;
 use texture::{SpritesBuilder, Texture};
+use viewport::{Viewport, VirtualResolution};
+
+pub(crate) use gl_objects::report_live_objects;
+
+const DEFAULT_FONT: &str = "resources/img/terminal8x8.png";
 
 #[derive(Clone)]
 pub enum ImgKind {
@@ -17,130 +41,362 @@ pub enum ImgKind {
     JPEG,
     JPG,
 }
+impl ImgKind {
+    /// Guesses the kind from the file extension, PNG when unknown.
+    pub fn from_path(img_path: &str) -> Self {
+        let extension = std::path::Path::new(img_path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("jpeg") => ImgKind::JPEG,
+            Some("jpg") => ImgKind::JPG,
+            _ => ImgKind::PNG,
+        }
+    }
+}
 
 pub struct Renderer {
-    width: u32,
-    height: u32,
-    gl_objects: GlObjects,
-    symbols: Vec<Texture>,
+    projection: glm::Mat4,
+    window: glm::Vec2,
+    virtual_resolution: Option<VirtualResolution>,
+    viewport: Viewport,
+    commands: Vec<DrawCommand>,
+    backend: Box<dyn RenderBackend>,
+    font: Handle<Font>,
+    /// A white pixel, the texture shapes are drawn with.
+    white: Texture,
+    space: Space,
+    layer: Layer,
+    depth: f32,
+    parallax: [glm::Vec2; Layer::ALL.len()],
+    pub camera: Camera2D,
     pub res_manager: ResourcesManager,
 }
 impl Renderer {
     pub fn new(width: u32, height: u32) -> Self {
-        let gl_objects = GlObjectsBuilder::new()
-            .vertices(vec![
-                // pos      // tex
-                0.0, 1.0, 0.0, 1.0, //0
-                1.0, 1.0, 1.0, 1.0, //1
-                1.0, 0.0, 1.0, 0.0, //2
-                0.0, 0.0, 0.0, 0.0, //3
-            ])
-            .indices(vec![
-                0, 1, 2, //0
-                2, 3, 0, //1
-            ])
-            .layout(MyTypes::FLOAT, 2)
-            .layout(MyTypes::FLOAT, 2)
-            .build();
-
-        let symbols = SpritesBuilder::init("resources/img/terminal8x8.png", ImgKind::PNG)
+        Self::try_new(width, height).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// GL renderer; fails when the font or the sprite shader cannot be loaded.
+    pub fn try_new(width: u32, height: u32) -> Result<Self> {
+        let mut res_manager = ResourcesManager::new();
+        let shader = res_manager.try_load_shader(SPRITE_SHADER)?;
+        Self::try_build(width, height, Box::new(GlBackend::new(shader)), res_manager)
+    }
+
+    /// Renderer that never touches OpenGL, for panels without a GL context.
+    /// The last frame is kept by a `RecordingBackend`.
+    pub fn headless(width: u32, height: u32) -> Self {
+        Self::with_backend(width, height, Box::new(RecordingBackend::last_frame()))
+    }
+
+    pub fn with_backend(width: u32, height: u32, backend: Box<dyn RenderBackend>) -> Self {
+        Self::try_with_backend(width, height, backend).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_with_backend(
+        width: u32,
+        height: u32,
+        backend: Box<dyn RenderBackend>,
+    ) -> Result<Self> {
+        Self::try_build(width, height, backend, ResourcesManager::new())
+    }
+
+    fn try_build(
+        width: u32,
+        height: u32,
+        backend: Box<dyn RenderBackend>,
+        mut res_manager: ResourcesManager,
+    ) -> Result<Self> {
+        let storage = backend.texture_storage();
+        res_manager.set_texture_storage(storage);
+        let symbols = SpritesBuilder::init(DEFAULT_FONT, ImgKind::PNG)
             .with_rows(16, 8)
-            .with_columns(16, 8)
-            .build();
-
-        Self {
-            width,
-            height,
-            gl_objects,
-            symbols,
-            res_manager: ResourcesManager::new(),
+            .with_columns(16, 8);
+        let font = Handle::new(
+            &symbols.key(),
+            Font::from_grid(&*res_manager.try_load_sprite_sheet(symbols)?),
+        );
+
+        let window = glm::vec2(width as f32, height as f32);
+        let mut renderer = Self {
+            projection: glm::Mat4::identity(),
+            window,
+            virtual_resolution: None,
+            viewport: Viewport::full(window),
+            commands: Vec::new(),
+            backend,
+            font,
+            white: Texture::new(
+                DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([255; 4]))),
+                ImgKind::PNG,
+                storage,
+            ),
+            space: Space::World,
+            layer: Layer::default(),
+            depth: 0.0,
+            parallax: [glm::vec2(1.0, 1.0); Layer::ALL.len()],
+            camera: Camera2D::new(width as f32, height as f32),
+            res_manager,
+        };
+        renderer.update_viewport();
+        Ok(renderer)
+    }
+
+    /// Takes the new size of the window, in pixels. Panels call it when the
+    /// window is resized.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.window = glm::vec2(width as f32, height as f32);
+        self.update_viewport();
+    }
+
+    /// Draws at a fixed logical size scaled up to the window, or at the
+    /// window's own size with `None`, the default.
+    pub fn set_virtual_resolution(&mut self, resolution: Option<VirtualResolution>) {
+        self.virtual_resolution = resolution;
+        self.update_viewport();
+    }
+
+    pub fn virtual_resolution(&self) -> Option<VirtualResolution> {
+        self.virtual_resolution
+    }
+
+    /// Size of the screen space draw calls are given in: the virtual
+    /// resolution if set, the window otherwise.
+    pub fn size(&self) -> glm::Vec2 {
+        match self.virtual_resolution {
+            Some(resolution) => glm::vec2(resolution.width as f32, resolution.height as f32),
+            None => self.window,
         }
-        .init()
     }
 
-    fn init(mut self) -> Self {
-        let projection: nalgebra_glm::Mat4 =
-            nalgebra_glm::ortho(0.0, self.width as f32, self.height as f32, 0.0, -1.0, 1.0);
-        let shader = self
-            .res_manager
-            .load_shader("resources/shaders/sprite.shader");
-        shader.activate();
-        shader.set_uniform_1i("image", 0);
-        shader.set_matrix4("projection", &projection);
-        self
+    /// Part of the window the frame is drawn into.
+    pub fn viewport(&self) -> Viewport {
+        self.viewport
+    }
+
+    /// Maps window pixels, e.g. the cursor position, to screen space. `None`
+    /// while the window has no area.
+    pub fn window_to_screen(&self, point: glm::Vec2) -> Option<glm::Vec2> {
+        self.viewport.window_to_logical(point, self.size())
+    }
+
+    fn update_viewport(&mut self) {
+        let size = self.size();
+        self.viewport = match &self.virtual_resolution {
+            Some(resolution) => Viewport::letterbox(self.window, resolution),
+            None => Viewport::full(self.window),
+        };
+        self.projection = glm::ortho(0.0, size.x, size.y, 0.0, -1.0, 1.0);
+        self.camera.resize_viewport(size.x, size.y);
+        self.backend.set_viewport(self.window, &self.viewport);
     }
 
+    /// Draws `texture` as a `size` quad with its top left corner at
+    /// `position`, rotated around its center. `draw` takes more options.
     pub fn draw_image(
         &mut self,
         position: glm::Vec2,
         size: glm::Vec2,
         rotate: f32,
-        color: glm::Vec3,
+        color: impl Into<Color>,
         texture: &Texture,
     ) {
-        self.transform_image(position, size, rotate, color);
-        self.gl_objects.vao.bind();
-
-        texture.bind();
-        self.draw();
+        self.push(DrawCommand {
+            texture: texture.clone(),
+            position,
+            size,
+            rotate,
+            color: color.into(),
+            layer: self.layer,
+            depth: self.depth,
+            vertices: None,
+        });
     }
 
-    pub fn println(&mut self, x: f32, y: f32, size: f32, line: &str) {
-        for (i, symbol) in line.char_indices() {
-            let symbol_texture = self.symbols[symbol as usize];
-            self.draw_image(
-                glm::vec2(x + (i as f32 * size) as f32, y),
-                glm::vec2(size, size),
-                0.0,
-                glm::vec3(1.0, 1.0, 1.0),
-                &symbol_texture,
-            );
+    /// Draws `texture`, or the part of it `params.source` picks, as `params` say.
+    pub fn draw(&mut self, texture: &Texture, params: &DrawParams) {
+        let mut texture = match params.source {
+            Some(source) => texture.sub_texture(source.x, source.y, source.z, source.w),
+            None => texture.clone(),
+        };
+        let flip_x = params.flip_x != (params.scale.x < 0.0);
+        let flip_y = params.flip_y != (params.scale.y < 0.0);
+        let mut pivot = params.pivot;
+        // mirroring the UVs flips the picture within the quad, and mirroring
+        // the pivot flips the quad around it
+        if flip_x {
+            texture.region.swap_rows(0, 2);
+            pivot.x = 1.0 - pivot.x;
+        }
+        if flip_y {
+            texture.region.swap_rows(1, 3);
+            pivot.y = 1.0 - pivot.y;
         }
+
+        let size = params
+            .size
+            .unwrap_or_else(|| glm::vec2(texture.width as f32, texture.height as f32))
+            .component_mul(&glm::abs(&params.scale));
+        // commands rotate around their center, so move the center to where
+        // rotating around the pivot takes it
+        let center = params.position - pivot.component_mul(&size) + size * 0.5;
+        let center =
+            params.position + glm::rotate_vec2(&(center - params.position), params.rotation);
+        self.push(DrawCommand {
+            texture,
+            position: center - size * 0.5,
+            size,
+            rotate: params.rotation,
+            color: params.color,
+            layer: params.layer.unwrap_or(self.layer),
+            depth: params.depth.unwrap_or(self.depth),
+            vertices: None,
+        });
     }
 
-    fn transform_image(
-        &mut self,
-        position: glm::Vec2,
-        size: glm::Vec2,
-        rotate: f32,
-        color: glm::Vec3,
-    ) {
-        let mut model = glm::Mat4x4::from_diagonal_element(1.0);
-        model = glm::translate(&model, &glm::vec3(position.x, position.y, 0.0));
-        model = glm::translate(&model, &glm::vec3(0.5 * size.x, 0.5 * size.y, 0.0)); // move
-        model = glm::rotate(&model, rotate, &glm::vec3(0.0, 0.0, 1.0));
-        model = glm::translate(&model, &glm::vec3(-0.5 * size.x, -0.5 * size.y, 0.0)); // move
-        model = glm::scale(&model, &glm::vec3(size.x, size.y, 1.0));
-
-        let shader = self
-            .res_manager
-            .load_shader("resources/shaders/sprite.shader");
-        shader.activate();
-        shader.set_matrix4("model", &model);
-        shader.set_vector_3f("spriteColor", color.x, color.y, color.z);
-    }
-
-    fn draw(&self) {
-        unsafe {
-            if self.gl_objects.ebo.is_some() {
-                gl::DrawElements(
-                    gl::TRIANGLES,
-                    self.gl_objects.vbo.count as GLsizei,
-                    gl::UNSIGNED_INT,
-                    ptr::null(),
-                );
-            } else {
-                gl::DrawArrays(gl::TRIANGLES, 0, self.gl_objects.vbo.count as GLsizei);
+    /// Space the following draw calls are given in, `Space::World` by default.
+    pub fn set_space(&mut self, space: Space) {
+        self.space = space;
+    }
+
+    pub fn space(&self) -> Space {
+        self.space
+    }
+
+    /// Layer the following draw calls go to, `Layer::Entities` by default.
+    /// `DrawParams::with_layer` overrides it for a single sprite.
+    pub fn set_layer(&mut self, layer: Layer) {
+        self.layer = layer;
+    }
+
+    pub fn layer(&self) -> Layer {
+        self.layer
+    }
+
+    /// Depth within their layer of the following images, text and shapes,
+    /// 0 by default. `DrawParams::with_depth` overrides it for a single sprite.
+    pub fn set_depth(&mut self, depth: f32) {
+        self.depth = depth;
+    }
+
+    pub fn depth(&self) -> f32 {
+        self.depth
+    }
+
+    /// How much of the camera's movement `layer` follows in world space: 1,
+    /// the default, moves with the world, smaller factors scroll slower like
+    /// distant scenery, and 0 stays put.
+    pub fn set_parallax(&mut self, layer: Layer, factor: glm::Vec2) {
+        self.parallax[layer as usize] = factor;
+    }
+
+    pub fn parallax(&self, layer: Layer) -> glm::Vec2 {
+        self.parallax[layer as usize]
+    }
+
+    /// Font of `println`, the monospace `terminal8x8.png` grid.
+    pub fn default_font(&self) -> Handle<Font> {
+        self.font.clone()
+    }
+
+    /// Draws `text` with the top left corner of its box at `position`: its
+    /// shadow first, then its outline, then the glyphs.
+    pub fn draw_text(&mut self, font: &Font, position: glm::Vec2, style: &TextStyle, text: &str) {
+        let glyphs = font.layout(text, style).glyphs;
+        if let Some(shadow) = style.shadow {
+            for glyph in &glyphs {
+                let color = shadow.color.with_alpha(shadow.color.a * glyph.color.a);
+                let position = position + glyph.position + shadow.offset;
+                self.draw_image(position, glyph.size, 0.0, color, &glyph.texture);
+            }
+        }
+        if let Some(outline) = style.outline {
+            // the glyph stamped around itself, in the 8 directions
+            let directions = [
+                (-1.0, -1.0),
+                (0.0, -1.0),
+                (1.0, -1.0),
+                (-1.0, 0.0),
+                (1.0, 0.0),
+                (-1.0, 1.0),
+                (0.0, 1.0),
+                (1.0, 1.0),
+            ];
+            for glyph in &glyphs {
+                let color = outline.color.with_alpha(outline.color.a * glyph.color.a);
+                for (x, y) in directions {
+                    let offset = glm::vec2(x, y) * outline.width;
+                    let position = position + glyph.position + offset;
+                    self.draw_image(position, glyph.size, 0.0, color, &glyph.texture);
+                }
             }
         }
-        self.gl_objects.vao.unbind();
+        for glyph in &glyphs {
+            let position = position + glyph.position;
+            self.draw_image(position, glyph.size, 0.0, glyph.color, &glyph.texture);
+        }
     }
 
-    pub fn clear(&self) {
-        unsafe {
-            gl::ClearColor(0.0, 0.0, 0.0, 0.0);
-            gl::Clear(gl::COLOR_BUFFER_BIT);
+    /// Draws `line` in the default font, `size` pixels per char.
+    pub fn println(&mut self, x: f32, y: f32, size: f32, line: &str) {
+        let font = self.font.clone();
+        self.draw_text(&font, glm::vec2(x, y), &TextStyle::new(size), line);
+    }
+
+    /// Records the command, moving world space quads into screen space.
+    fn push(&mut self, mut command: DrawCommand) {
+        if self.space == Space::World {
+            let center = command.position + command.size * 0.5 + self.parallax_shift(command.layer);
+            command.size *= self.camera.zoom;
+            command.position = self.camera.world_to_screen(center) - command.size * 0.5;
+            command.rotate -= self.camera.rotation;
         }
+        self.commands.push(command);
+    }
+
+    /// Moves world space points so the camera sees them as if it had only
+    /// moved by the parallax factor of `layer`, from where it maps world
+    /// to screen 1:1.
+    fn parallax_shift(&self, layer: Layer) -> glm::Vec2 {
+        let parallax = self.parallax[layer as usize];
+        let moved = self.camera.position - self.camera.viewport() * 0.5;
+        moved - moved.component_mul(&parallax)
+    }
+
+    /// Starts a new frame: drops the commands recorded so far, picks up edited
+    /// shaders and clears the screen.
+    pub fn clear(&mut self) {
+        self.commands.clear();
+        self.res_manager.reload_modified();
+        self.backend.clear();
+    }
+
+    /// Hands the commands recorded since `clear` over to the backend.
+    pub fn flush(&mut self) {
+        // stable, so the commands are still in call order at equal depths:
+        // backends only batch runs of a texture that are already adjacent
+        self.commands
+            .sort_by(|a, b| a.layer.cmp(&b.layer).then(a.depth.total_cmp(&b.depth)));
+        self.backend.render(&self.projection, &self.commands);
+    }
+
+    /// Sprites, batches and draw calls of the last rendered frame.
+    pub fn stats(&self) -> RenderStats {
+        self.backend.stats()
+    }
+
+    /// Copy of the last rendered frame, e.g. to dump it to PNG.
+    /// `None` when the backend cannot read frames back.
+    pub fn snapshot(&self) -> Option<image::RgbaImage> {
+        self.backend.snapshot()
+    }
+
+    /// Commands recorded during the current frame, already in screen space.
+    /// In call order until `flush` sorts them by layer and depth.
+    pub fn commands(&self) -> &[DrawCommand] {
+        &self.commands
     }
 
     pub fn print_gl_version(&self) {
@@ -153,83 +409,122 @@ impl Renderer {
     }
 }
 
-pub struct GlObjects {
-    _vertices: Vec<f32>,
-    _indices: Vec<i32>,
-    vao: VAO,
-    vbo: VBO,
-    ebo: Option<EBO>,
-}
-impl GlObjects {
-    pub fn destroy(&mut self) {
-        self.vao.delete();
-        self.vbo.delete();
-        if let Some(_) = self.ebo {
-            self.ebo.as_mut().unwrap().delete();
-        }
-    }
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testing::{pixel, recorded};
 
-struct GlObjectsBuilder {
-    vertices: Vec<f32>,
-    indices: Vec<i32>,
-    vao: VAO,
-    vbo: Option<VBO>,
-    ebo: Option<EBO>,
-    layouts: VertexesLayout,
-}
-impl GlObjectsBuilder {
-    pub fn new() -> Self {
-        let vao = VAO::new();
-        vao.bind();
-        Self {
-            vertices: Vec::new(),
-            indices: Vec::new(),
-            vao,
-            vbo: None,
-            ebo: None,
-            layouts: VertexesLayout::new(),
-        }
-    }
+    #[test]
+    fn flush_sorts_by_layer_and_depth_keeping_call_order() {
+        let (mut renderer, frames) = recorded();
+        let (first, second) = (pixel(), pixel());
+        let at = |x: f32| DrawParams::at(glm::vec2(x, 0.0));
+        renderer.clear();
+        renderer.draw(&second, &at(0.0));
+        renderer.draw(&first, &at(1.0));
+        renderer.draw(&second, &at(2.0));
+        renderer.draw(&first, &at(3.0).with_depth(-1.0));
+        renderer.draw(&second, &at(4.0).with_layer(Layer::Background));
+        renderer.flush();
 
-    fn vertices(mut self, vertices: Vec<f32>) -> Self {
-        self.vertices = vertices;
-        self.vbo = Some(VBO::new(&self.vertices[..]));
-        self
+        let frames = frames.borrow();
+        let order: Vec<(f32, u32)> = frames[0]
+            .iter()
+            .map(|command| (command.position.x, command.texture.id()))
+            .collect();
+        let (first, second) = (first.id(), second.id());
+        assert_eq!(
+            order,
+            [
+                (4.0, second),
+                (3.0, first),
+                (0.0, second),
+                (1.0, first),
+                (2.0, second)
+            ]
+        );
     }
 
-    fn indices(mut self, indices: Vec<i32>) -> Self {
-        self.indices = indices;
-        self.ebo = Some(EBO::new(&self.indices[..]));
-        self
-    }
+    #[test]
+    fn parallax_scales_the_camera_movement() {
+        let (mut renderer, frames) = recorded();
+        let texture = pixel();
+        renderer.set_parallax(Layer::Background, glm::vec2(0.0, 0.0));
+        renderer.set_parallax(Layer::Tiles, glm::vec2(0.5, 1.0));
+        let at = DrawParams::at(glm::vec2(10.0, 10.0));
+        for moved in [glm::vec2(0.0, 0.0), glm::vec2(40.0, 20.0)] {
+            renderer.clear();
+            renderer.camera.position = glm::vec2(160.0, 120.0) + moved;
+            renderer.draw(&texture, &at.with_layer(Layer::Background));
+            renderer.draw(&texture, &at.with_layer(Layer::Tiles));
+            renderer.draw(&texture, &at);
+            renderer.flush();
+        }
 
-    fn layout(mut self, tp: MyTypes, size: usize) -> Self {
-        let el = VertexBufferElement::new(size, tp, false);
-        self.layouts.elements.push(el);
-        self.layouts.stride += match tp {
-            MyTypes::FLOAT => mem::size_of::<GLfloat>(),
-            MyTypes::INT => mem::size_of::<GLsizei>(),
-            MyTypes::CHAR => mem::size_of::<GLchar>(),
-        } as GLsizei
-            * size as GLsizei;
-        self
+        let frames = frames.borrow();
+        let positions = |frame: usize| -> Vec<glm::Vec2> {
+            frames[frame]
+                .iter()
+                .map(|command| command.position)
+                .collect()
+        };
+        // a still camera shows every layer 1:1
+        assert_eq!(positions(0), [glm::vec2(10.0, 10.0); 3]);
+        assert_eq!(
+            positions(1),
+            [
+                glm::vec2(10.0, 10.0),
+                glm::vec2(-10.0, -10.0),
+                glm::vec2(-30.0, -10.0)
+            ]
+        );
     }
 
-    fn build(self) -> GlObjects {
-        self.vao.link(&self.vbo.as_ref().unwrap(), &self.layouts);
-        self.vao.unbind();
-        self.vbo.as_ref().unwrap().unbind();
-        if self.ebo.is_some() {
-            self.ebo.as_ref().unwrap().unbind();
-        }
+    #[test]
+    fn text_draws_its_shadow_then_its_outline_then_its_glyphs() {
+        let (mut renderer, frames) = recorded();
+        let font = renderer.default_font();
+        let style = TextStyle::new(8.0)
+            .with_color(Color::WHITE.with_alpha(0.5))
+            .with_markup()
+            .with_shadow(glm::vec2(2.0, 3.0), Color::BLACK)
+            .with_outline(1.0, Color::BLUE);
+        renderer.clear();
+        renderer.set_space(Space::Screen);
+        renderer.draw_text(&font, glm::vec2(10.0, 20.0), &style, "a[red]b");
+        renderer.flush();
 
-        GlObjects {
-            _vertices: self.vertices,
-            _indices: self.indices,
-            vao: self.vao,
-            vbo: self.vbo.unwrap(),
-            ebo: if self.ebo.is_some() { self.ebo } else { None },
-        }
+        let frames = frames.borrow();
+        let drawn: Vec<(glm::Vec2, Color)> = frames[0]
+            .iter()
+            .map(|command| (command.position, command.color))
+            .collect();
+        assert_eq!(drawn.len(), 2 + 2 * 8 + 2);
+        // glyphs are 8 pixels apart, each color faded by the glyph's
+        let half = |color: Color| color.with_alpha(0.5);
+        assert_eq!(
+            drawn[..2],
+            [
+                (glm::vec2(12.0, 23.0), half(Color::BLACK)),
+                (glm::vec2(20.0, 23.0), half(Color::BLACK))
+            ]
+        );
+        let around = |x: f32, y: f32| -> Vec<(glm::Vec2, Color)> {
+            [-1.0, 0.0, 1.0]
+                .iter()
+                .flat_map(|dy| [-1.0, 0.0, 1.0].map(|dx| (dx, *dy)))
+                .filter(|offset| *offset != (0.0, 0.0))
+                .map(|(dx, dy)| (glm::vec2(x + dx, y + dy), half(Color::BLUE)))
+                .collect()
+        };
+        assert_eq!(drawn[2..10], around(10.0, 20.0));
+        assert_eq!(drawn[10..18], around(18.0, 20.0));
+        assert_eq!(
+            drawn[18..],
+            [
+                (glm::vec2(10.0, 20.0), half(Color::WHITE)),
+                (glm::vec2(18.0, 20.0), half(Color::RED))
+            ]
+        );
     }
 }
@@ -1,149 +1,183 @@
+use crate::error::{Error, Result};
+use crate::renderer::gl_objects::GlKind;
 use gl::types::*;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::ffi::CString;
 use std::ptr;
 
-#[derive(Clone, Debug)]
+/// A linked GL program. Shared through `Handle<Shader>`, so the program can be
+/// swapped in place when its source is hot-reloaded.
+#[derive(Debug)]
 pub struct Shader {
-    pub id: u32,
-    cache_uniform_location: HashMap<String, i32>,
+    id: Cell<u32>,
+    cache_uniform_location: RefCell<HashMap<String, i32>>,
 }
 
 impl Shader {
-    pub fn new(source: (String, String)) -> Self {
+    /// Compiles and links `source` (vertex, fragment). `path` only names the
+    /// shader in errors.
+    pub fn new(path: &str, source: (String, String)) -> Result<Self> {
         let (vertex_code, fragment_code) = source;
-        let vertex_src = CString::new(vertex_code.as_bytes()).unwrap();
-        let fragment_src = CString::new(fragment_code.as_bytes()).unwrap();
+        let error = |stage: &str, log: String| Error::Shader {
+            path: path.to_string(),
+            stage: stage.to_string(),
+            log,
+        };
+        let vertex_src =
+            CString::new(vertex_code.as_bytes()).map_err(|e| error("VERTEX", e.to_string()))?;
+        let fragment_src =
+            CString::new(fragment_code.as_bytes()).map_err(|e| error("FRAGMENT", e.to_string()))?;
 
         let id;
         unsafe {
             let vertex_shader = gl::CreateShader(gl::VERTEX_SHADER);
             gl::ShaderSource(vertex_shader, 1, &vertex_src.as_ptr(), ptr::null());
             gl::CompileShader(vertex_shader);
-            compile_errors(vertex_shader, "VERTEX");
             let fragment_shader = gl::CreateShader(gl::FRAGMENT_SHADER);
             gl::ShaderSource(fragment_shader, 1, &fragment_src.as_ptr(), ptr::null());
             gl::CompileShader(fragment_shader);
-            compile_errors(fragment_shader, "FRAGMENT");
+            let compiled = compile_errors(vertex_shader, "VERTEX")
+                .map_err(|log| error("VERTEX", log))
+                .and_then(|_| {
+                    compile_errors(fragment_shader, "FRAGMENT")
+                        .map_err(|log| error("FRAGMENT", log))
+                });
+            if let Err(e) = compiled {
+                gl::DeleteShader(vertex_shader);
+                gl::DeleteShader(fragment_shader);
+                return Err(e);
+            }
             id = gl::CreateProgram();
             gl::AttachShader(id, vertex_shader);
             gl::AttachShader(id, fragment_shader);
             gl::LinkProgram(id);
-            compile_errors(id, "PROGRAM");
             gl::DeleteShader(vertex_shader);
             gl::DeleteShader(fragment_shader);
+            if let Err(log) = compile_errors(id, "PROGRAM") {
+                gl::DeleteProgram(id);
+                return Err(error("PROGRAM", log));
+            }
         }
-        Self {
-            id,
-            cache_uniform_location: HashMap::new(),
-        }
+        GlKind::Program.created();
+        Ok(Self {
+            id: Cell::new(id),
+            cache_uniform_location: RefCell::new(HashMap::new()),
+        })
     }
 
-    pub fn activate(&self) {
-        unsafe {
-            gl::UseProgram(self.id);
-        }
+    pub fn id(&self) -> u32 {
+        self.id.get()
     }
 
-    pub fn delete(&self) {
+    /// Takes over the program of `other`, which leaves with the current one
+    /// and deletes it when dropped.
+    pub(crate) fn replace_program(&self, other: Shader) {
+        self.id.swap(&other.id);
+        self.cache_uniform_location.borrow_mut().clear();
+    }
+
+    pub fn activate(&self) {
         unsafe {
-            gl::DeleteProgram(self.id);
+            gl::UseProgram(self.id());
         }
     }
 
-    pub fn set_uniform_4f(&mut self, name: &str, v0: f32, v1: f32, v2: f32, v3: f32) {
+    pub fn set_uniform_4f(&self, name: &str, v0: f32, v1: f32, v2: f32, v3: f32) {
         unsafe {
             gl::Uniform4f(self.get_uniform_location(name), v0, v1, v2, v3);
         }
     }
 
-    pub fn set_uniform_1i(&mut self, name: &str, v: i32) {
+    pub fn set_uniform_1i(&self, name: &str, v: i32) {
         unsafe {
             gl::Uniform1i(self.get_uniform_location(name), v);
         }
     }
 
-    pub fn set_uniform_1f(&mut self, name: &str, v: f32) {
+    pub fn set_uniform_1f(&self, name: &str, v: f32) {
         unsafe {
             gl::Uniform1f(self.get_uniform_location(name), v);
         }
     }
 
-    pub fn set_vector_3f(&mut self, name: &str, v0: f32, v1: f32, v2: f32) {
+    pub fn set_vector_3f(&self, name: &str, v0: f32, v1: f32, v2: f32) {
         unsafe {
             gl::Uniform3f(self.get_uniform_location(name), v0, v1, v2);
         }
     }
 
-    pub fn set_matrix4(&mut self, name: &str, matrix: &nalgebra_glm::Mat4) {
+    pub fn set_matrix4(&self, name: &str, matrix: &nalgebra_glm::Mat4) {
         unsafe {
             gl::UniformMatrix4fv(self.get_uniform_location(name), 1, 0, matrix.as_ptr());
         }
     }
 
-    fn get_uniform_location(&mut self, name: &str) -> i32 {
-        if self.cache_uniform_location.get(name).is_some() {
-            return self.cache_uniform_location.get(name).unwrap().clone();
+    fn get_uniform_location(&self, name: &str) -> i32 {
+        let mut cache_uniform_location = self.cache_uniform_location.borrow_mut();
+        if let Some(location) = cache_uniform_location.get(name) {
+            return *location;
         }
         let var_name = CString::new(name.as_bytes()).unwrap();
         let location;
         unsafe {
-            location = gl::GetUniformLocation(self.id, var_name.as_ptr());
+            location = gl::GetUniformLocation(self.id(), var_name.as_ptr());
             if location == -1 {
-                println!("Uniform {} doesnt exist!", name);
+                log::warn!("Uniform {} doesnt exist!", name);
             }
-            self.cache_uniform_location
-                .insert(name.to_string(), location);
-            println!("Map {:?}", self.cache_uniform_location);
+            cache_uniform_location.insert(name.to_string(), location);
+            log::debug!("Map {:?}", cache_uniform_location);
             location
         }
     }
 }
 
-fn compile_errors(shader_id: u32, tp: &str) {
+impl Drop for Shader {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteProgram(self.id());
+        }
+        GlKind::Program.deleted();
+    }
+}
+
+/// Returns the info log when `shader_id` failed to compile, or to link for "PROGRAM".
+fn compile_errors(shader_id: u32, tp: &str) -> std::result::Result<(), String> {
     // Stores status of compilation
     let mut has_compiled = gl::FALSE as GLint;
-    // Character array to store error message in
-    let mut info_log: Vec<u8> = Vec::with_capacity(1024);
-    if tp != "PROGRAM" {
-        unsafe {
+    let mut log_length = 0;
+    unsafe {
+        if tp != "PROGRAM" {
             gl::GetShaderiv(shader_id, gl::COMPILE_STATUS, &mut has_compiled);
-        }
-        if has_compiled == gl::FALSE as GLint {
-            unsafe {
-                gl::GetShaderInfoLog(
-                    shader_id,
-                    1024,
-                    ptr::null_mut(),
-                    info_log.as_mut_ptr() as *mut GLchar,
-                );
-            }
-            println!(
-                "ERROR::SHADER_COMPILATION_ERROR of type: {}\n{}\n \
-                          -- --------------------------------------------------- -- ",
-                tp,
-                std::str::from_utf8(&info_log[..]).unwrap()
-            );
-        }
-    } else {
-        unsafe {
+            gl::GetShaderiv(shader_id, gl::INFO_LOG_LENGTH, &mut log_length);
+        } else {
             gl::GetProgramiv(shader_id, gl::LINK_STATUS, &mut has_compiled);
+            gl::GetProgramiv(shader_id, gl::INFO_LOG_LENGTH, &mut log_length);
         }
-        if has_compiled == gl::FALSE as GLint {
-            unsafe {
-                gl::GetProgramInfoLog(
-                    shader_id,
-                    1024,
-                    ptr::null_mut(),
-                    info_log.as_mut_ptr() as *mut GLchar,
-                );
-            }
-            println!(
-                "ERROR::SHADER_COMPILATION_ERROR of type: {}\n{}\n \
-                          -- --------------------------------------------------- -- ",
-                tp,
-                std::str::from_utf8(&info_log[..]).unwrap()
+    }
+    if has_compiled != gl::FALSE as GLint {
+        return Ok(());
+    }
+    // Character array to store error message in
+    let mut info_log = vec![0u8; log_length.max(1) as usize];
+    unsafe {
+        if tp != "PROGRAM" {
+            gl::GetShaderInfoLog(
+                shader_id,
+                log_length,
+                ptr::null_mut(),
+                info_log.as_mut_ptr() as *mut GLchar,
+            );
+        } else {
+            gl::GetProgramInfoLog(
+                shader_id,
+                log_length,
+                ptr::null_mut(),
+                info_log.as_mut_ptr() as *mut GLchar,
             );
         }
     }
+    Err(String::from_utf8_lossy(&info_log)
+        .trim_end_matches('\0')
+        .to_string())
 }
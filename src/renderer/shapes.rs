@@ -0,0 +1,224 @@
+//! Untextured shapes: rectangles, lines, circles and convex polygons. They
+//! are cut into quads of the renderer's white pixel, so they batch, sort by
+//! layer and depth and follow the camera like sprites do.
+
+use crate::renderer::backends::common::DrawCommand;
+use crate::renderer::camera::Space;
+use crate::renderer::color::Color;
+use crate::renderer::Renderer;
+use nalgebra_glm as glm;
+use std::f32::consts::TAU;
+
+impl Renderer {
+    /// Fills the `size` rectangle with its top left corner at `position`.
+    pub fn fill_rect(&mut self, position: glm::Vec2, size: glm::Vec2, color: impl Into<Color>) {
+        let corners = [
+            position,
+            position + glm::vec2(size.x, 0.0),
+            position + size,
+            position + glm::vec2(0.0, size.y),
+        ];
+        self.push_vertices(corners, color.into());
+    }
+
+    /// Outlines the rectangle with edges `thickness` wide, inside it so a
+    /// hitbox's outline covers exactly the hitbox.
+    pub fn stroke_rect(
+        &mut self,
+        position: glm::Vec2,
+        size: glm::Vec2,
+        thickness: f32,
+        color: impl Into<Color>,
+    ) {
+        let color = color.into();
+        let t = thickness.min(size.x * 0.5).min(size.y * 0.5);
+        let side = size.y - 2.0 * t;
+        self.fill_rect(position, glm::vec2(size.x, t), color);
+        self.fill_rect(
+            position + glm::vec2(0.0, size.y - t),
+            glm::vec2(size.x, t),
+            color,
+        );
+        self.fill_rect(position + glm::vec2(0.0, t), glm::vec2(t, side), color);
+        self.fill_rect(
+            position + glm::vec2(size.x - t, t),
+            glm::vec2(t, side),
+            color,
+        );
+    }
+
+    /// A line `thickness` wide from `from` to `to`, cut square at its ends.
+    pub fn draw_line(
+        &mut self,
+        from: glm::Vec2,
+        to: glm::Vec2,
+        thickness: f32,
+        color: impl Into<Color>,
+    ) {
+        self.push_line(from, to, thickness, 0.0, color.into());
+    }
+
+    pub fn fill_circle(&mut self, center: glm::Vec2, radius: f32, color: impl Into<Color>) {
+        self.fill_polygon(&circle(center, radius, segments(radius)), color);
+    }
+
+    /// Outlines the circle with a ring `thickness` wide, inside it.
+    pub fn stroke_circle(
+        &mut self,
+        center: glm::Vec2,
+        radius: f32,
+        thickness: f32,
+        color: impl Into<Color>,
+    ) {
+        let color = color.into();
+        let segments = segments(radius);
+        let outer = circle(center, radius, segments);
+        let inner = circle(center, (radius - thickness).max(0.0), segments);
+        for i in 0..outer.len() {
+            let next = (i + 1) % outer.len();
+            self.push_vertices([outer[i], outer[next], inner[next], inner[i]], color);
+        }
+    }
+
+    /// Fills a convex polygon, its `points` wound either way. Concave ones
+    /// come out wrong: split them into convex parts first.
+    pub fn fill_polygon(&mut self, points: &[glm::Vec2], color: impl Into<Color>) {
+        let color = color.into();
+        let Some((&first, rest)) = points.split_first() else {
+            return;
+        };
+        // fan out from the first point, two triangles per quad
+        let mut i = 0;
+        while i + 2 < rest.len() {
+            self.push_vertices([first, rest[i], rest[i + 1], rest[i + 2]], color);
+            i += 2;
+        }
+        if i + 1 < rest.len() {
+            self.push_vertices([first, rest[i], rest[i + 1], first], color);
+        }
+    }
+
+    /// Outlines the closed polygon through `points` with lines `thickness` wide.
+    pub fn stroke_polygon(
+        &mut self,
+        points: &[glm::Vec2],
+        thickness: f32,
+        color: impl Into<Color>,
+    ) {
+        let color = color.into();
+        if points.len() < 2 {
+            return;
+        }
+        for (i, from) in points.iter().enumerate() {
+            let to = points[(i + 1) % points.len()];
+            // extended by half the thickness, so the corners are filled
+            self.push_line(*from, to, thickness, thickness * 0.5, color);
+        }
+    }
+
+    /// Line quad, stretched by `extend` past both ends.
+    fn push_line(
+        &mut self,
+        from: glm::Vec2,
+        to: glm::Vec2,
+        thickness: f32,
+        extend: f32,
+        color: Color,
+    ) {
+        let length = glm::distance(&from, &to);
+        if length == 0.0 {
+            return;
+        }
+        let direction = (to - from) / length;
+        let normal = glm::vec2(-direction.y, direction.x) * (thickness * 0.5);
+        let (from, to) = (from - direction * extend, to + direction * extend);
+        self.push_vertices(
+            [from + normal, to + normal, to - normal, from - normal],
+            color,
+        );
+    }
+
+    /// Records a quad of the white pixel with the given corners, in the
+    /// current space, layer and depth.
+    fn push_vertices(&mut self, mut vertices: [glm::Vec2; 4], color: Color) {
+        if self.space == Space::World {
+            let shift = self.parallax_shift(self.layer);
+            for vertex in &mut vertices {
+                *vertex = self.camera.world_to_screen(*vertex + shift);
+            }
+        }
+        let (min, max) = vertices
+            .iter()
+            .fold((vertices[0], vertices[0]), |(min, max), vertex| {
+                (glm::min2(&min, vertex), glm::max2(&max, vertex))
+            });
+        self.commands.push(DrawCommand {
+            texture: self.white.clone(),
+            position: min,
+            size: max - min,
+            rotate: 0.0,
+            color,
+            layer: self.layer,
+            depth: self.depth,
+            vertices: Some(vertices),
+        });
+    }
+}
+
+/// About one every 4 pixels of the circumference.
+fn segments(radius: f32) -> usize {
+    ((radius * TAU / 4.0).ceil() as usize).clamp(12, 128)
+}
+
+fn circle(center: glm::Vec2, radius: f32, segments: usize) -> Vec<glm::Vec2> {
+    (0..segments)
+        .map(|i| {
+            let angle = i as f32 / segments as f32 * TAU;
+            center + glm::vec2(angle.cos(), angle.sin()) * radius
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::backends::recording_backend::RecordingBackend;
+    use crate::renderer::draw_params::DrawParams;
+    use crate::renderer::layer::Layer;
+
+    #[test]
+    fn shapes_take_the_current_layer_and_depth() {
+        let mut renderer = Renderer::with_backend(64, 64, Box::new(RecordingBackend::new()));
+        renderer.set_space(Space::Screen);
+        renderer.set_layer(Layer::Foreground);
+        renderer.set_depth(2.0);
+        renderer.fill_rect(glm::vec2(1.0, 2.0), glm::vec2(3.0, 4.0), Color::RED);
+
+        let command = &renderer.commands()[0];
+        assert_eq!((command.layer, command.depth), (Layer::Foreground, 2.0));
+        assert_eq!(command.color, Color::RED);
+        assert_eq!(
+            command.vertices,
+            Some([
+                glm::vec2(1.0, 2.0),
+                glm::vec2(4.0, 2.0),
+                glm::vec2(4.0, 6.0),
+                glm::vec2(1.0, 6.0),
+            ])
+        );
+    }
+
+    #[test]
+    fn shapes_sort_with_sprites_by_depth() {
+        let mut renderer = Renderer::with_backend(64, 64, Box::new(RecordingBackend::new()));
+        renderer.set_space(Space::Screen);
+        let sprite = renderer.white.clone();
+        renderer.set_depth(1.0);
+        renderer.stroke_rect(glm::Vec2::zeros(), glm::vec2(8.0, 8.0), 1.0, Color::RED);
+        renderer.draw(&sprite, &DrawParams::at(glm::Vec2::zeros()).with_depth(0.5));
+        renderer.flush();
+
+        let depths: Vec<f32> = renderer.commands().iter().map(|c| c.depth).collect();
+        assert_eq!(depths, [0.5, 1.0, 1.0, 1.0, 1.0]);
+    }
+}
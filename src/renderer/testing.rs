@@ -0,0 +1,22 @@
+//! Fixtures shared by the renderer's tests.
+
+use super::backends::recording_backend::{Recording, RecordingBackend};
+use super::texture::{Texture, TextureStorage};
+use super::{ImgKind, Renderer};
+use image::{DynamicImage, RgbaImage};
+
+/// A 320x240 renderer, with the frames it renders.
+pub(crate) fn recorded() -> (Renderer, Recording) {
+    let backend = RecordingBackend::new();
+    let frames = backend.frames();
+    (Renderer::with_backend(320, 240, Box::new(backend)), frames)
+}
+
+/// A texture of a single transparent pixel, a new one on every call.
+pub(crate) fn pixel() -> Texture {
+    Texture::new(
+        DynamicImage::ImageRgba8(RgbaImage::new(1, 1)),
+        ImgKind::PNG,
+        TextureStorage::Cpu,
+    )
+}
@@ -1,21 +1,85 @@
+use crate::error::{Error, Result};
+use crate::renderer::gl_objects::GlKind;
 use crate::renderer::ImgKind;
-use gl::types::*;
-use image::DynamicImage;
+use image::{DynamicImage, RgbaImage};
 use nalgebra_glm as glm;
+use std::fmt;
+use std::ops::Index;
 use std::os::raw::c_void;
 use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
 
-#[derive(Clone, Copy, Debug)]
+const FULL_REGION: glm::Vec4 = glm::Vec4::new(0.0, 0.0, 1.0, 1.0);
+
+// Ids handed out to textures kept on the CPU.
+static HEADLESS_TEXTURE_ID: AtomicU32 = AtomicU32::new(1);
+
+/// Where textures are created. The renderer's `ResourcesManager` uses the
+/// storage its backend draws from; builders used on their own upload to GL.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextureStorage {
+    /// Uploaded to the current GL context.
+    #[default]
+    Gpu,
+    /// Pixels kept in memory, for backends drawing without a GL context.
+    Cpu,
+}
+
+/// The GL texture itself, deleted once the last `Texture` using it is dropped.
+struct GlTexture {
+    id: u32,
+    // only kept for `TextureStorage::Cpu` textures, which have no GL texture
+    pixels: Option<RgbaImage>,
+}
+
+impl Drop for GlTexture {
+    fn drop(&mut self) {
+        if self.pixels.is_some() {
+            return;
+        }
+        unsafe {
+            gl::DeleteTextures(1, &self.id);
+        }
+        GlKind::Texture.deleted();
+    }
+}
+
+impl fmt::Debug for GlTexture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GlTexture").field("id", &self.id).finish()
+    }
+}
+
+/// A GL texture, or a sub-rectangle of one when it comes from an atlas.
+/// Cloning is cheap: clones share the GL texture.
+#[derive(Clone, Debug)]
 pub struct Texture {
-    pub id: u32,
+    gl: Arc<GlTexture>,
+    /// Size in pixels of the region.
     pub width: u32,
     pub height: u32,
+    /// UV rectangle (left, top, right, bottom) of the region within the GL texture.
+    pub region: glm::Vec4,
 }
 impl Texture {
-    fn new(image: DynamicImage, kind: ImgKind) -> Self {
+    pub(crate) fn new(image: DynamicImage, kind: ImgKind, storage: TextureStorage) -> Self {
         let width = image.width();
         let height = image.height();
 
+        if storage == TextureStorage::Cpu {
+            let gl = GlTexture {
+                id: HEADLESS_TEXTURE_ID.fetch_add(1, Ordering::Relaxed),
+                pixels: Some(image.to_rgba8()),
+            };
+            return Self {
+                gl: Arc::new(gl),
+                width,
+                height,
+                region: FULL_REGION,
+            };
+        }
+
         let img_bytes = image.as_bytes();
 
         let mut id = 0;
@@ -28,8 +92,9 @@ impl Texture {
             gl::BindTexture(gl::TEXTURE_2D, id);
             gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
             gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32); // set texture wrapping to gl::REPEAT (default wrapping method)
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
+            // clamp, so sprites on the edge of an atlas do not bleed into the opposite side
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
             gl::TexImage2D(
                 gl::TEXTURE_2D,
                 0,
@@ -52,24 +117,49 @@ impl Texture {
             gl::GenerateMipmap(gl::TEXTURE_2D);
             gl::BindTexture(gl::TEXTURE_2D, 0);
         }
-        Self { id, width, height }
+        GlKind::Texture.created();
+        Self {
+            gl: Arc::new(GlTexture { id, pixels: None }),
+            width,
+            height,
+            region: FULL_REGION,
+        }
     }
 
-    pub fn bind(&self) {
-        unsafe {
-            gl::BindTexture(gl::TEXTURE_2D, self.id);
+    /// Name of the GL texture, shared by all its sub-textures.
+    pub fn id(&self) -> u32 {
+        self.gl.id
+    }
+
+    /// The `width` x `height` rectangle at (`x`, `y`) pixels of this texture,
+    /// sharing its GL texture.
+    pub fn sub_texture(&self, x: u32, y: u32, width: u32, height: u32) -> Self {
+        let (left, top, right, bottom) =
+            (self.region.x, self.region.y, self.region.z, self.region.w);
+        let u = |px: u32| left + (right - left) * px as f32 / self.width as f32;
+        let v = |px: u32| top + (bottom - top) * px as f32 / self.height as f32;
+        Self {
+            gl: Arc::clone(&self.gl),
+            width,
+            height,
+            region: glm::vec4(u(x), v(y), u(x + width), v(y + height)),
         }
     }
 
-    pub fn unbind(&self) {
+    /// CPU copy of the texture, only kept for `TextureStorage::Cpu` ones.
+    pub fn pixels(&self) -> Option<&RgbaImage> {
+        self.gl.pixels.as_ref()
+    }
+
+    pub fn bind(&self) {
         unsafe {
-            gl::BindTexture(gl::TEXTURE_2D, 0);
+            gl::BindTexture(gl::TEXTURE_2D, self.id());
         }
     }
 
-    pub fn delete(&mut self) {
+    pub fn unbind(&self) {
         unsafe {
-            gl::DeleteTextures(1, &mut self.id);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
         }
     }
 }
@@ -78,6 +168,7 @@ pub struct TextureBuilder {
     subimg: Option<glm::UVec4>,
     img_path: String,
     image_kind: ImgKind,
+    storage: TextureStorage,
 }
 impl TextureBuilder {
     pub fn init(img_path: &str, image_kind: ImgKind) -> Self {
@@ -85,21 +176,34 @@ impl TextureBuilder {
             img_path: img_path.to_string(),
             image_kind,
             subimg: None,
+            storage: TextureStorage::Gpu,
         }
     }
     pub fn subimg(mut self, subimg: glm::UVec4) -> Self {
         self.subimg = Some(subimg);
         self
     }
+    pub fn with_storage(mut self, storage: TextureStorage) -> Self {
+        self.storage = storage;
+        self
+    }
     pub fn build(self) -> Texture {
-        let mut image = load_image_from_file(&self.img_path);
+        self.try_build().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_build(self) -> Result<Texture> {
+        let mut image = load_image_from_file(&self.img_path)?;
         if let Some(subimage) = self.subimg {
             let subimg =
                 image::imageops::crop(&mut image, subimage.x, subimage.y, subimage.z, subimage.w)
                     .to_image();
-            return Texture::new(image::DynamicImage::ImageRgba8(subimg), self.image_kind);
+            return Ok(Texture::new(
+                image::DynamicImage::ImageRgba8(subimg),
+                self.image_kind,
+                self.storage,
+            ));
         }
-        Texture::new(image, self.image_kind)
+        Ok(Texture::new(image, self.image_kind, self.storage))
     }
 }
 
@@ -110,6 +214,7 @@ pub struct SpritesBuilder {
     columns: usize,
     sprite_width: u32,
     sprite_height: u32,
+    storage: TextureStorage,
 }
 
 impl SpritesBuilder {
@@ -121,6 +226,7 @@ impl SpritesBuilder {
             columns: 0,
             sprite_width: 0,
             sprite_height: 0,
+            storage: TextureStorage::Gpu,
         }
     }
     /// amount: How many rows on the atlas image
@@ -137,36 +243,186 @@ impl SpritesBuilder {
         self.sprite_height = sprite_height;
         self
     }
+
+    pub fn with_storage(mut self, storage: TextureStorage) -> Self {
+        self.storage = storage;
+        self
+    }
+    /// Uploads the sheet once and returns one sub-texture per cell, row by row.
     pub fn build(self) -> Vec<Texture> {
-        let mut image = load_image_from_file(&self.img_path);
+        self.try_build().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_build(self) -> Result<Vec<Texture>> {
+        Ok(self.try_build_sheet()?.sprites)
+    }
+
+    /// Like `try_build`, but keeps the sheet texture alongside its sprites.
+    ///
+    /// The cells are uploaded apart, each in a 1 pixel ring repeating its
+    /// edge, so nearest sampling at non-integer scales never picks a texel of
+    /// the neighbouring cell.
+    pub fn try_build_sheet(self) -> Result<SpriteSheet> {
+        let image = load_image_from_file(&self.img_path)?.to_rgba8();
+        let (cell_width, cell_height) = (self.sprite_width + 2, self.sprite_height + 2);
+        let mut pixels = RgbaImage::new(
+            (self.columns as u32 * cell_width).max(1),
+            (self.rows as u32 * cell_height).max(1),
+        );
+        for row in 0..self.rows as u32 {
+            for column in 0..self.columns as u32 {
+                let cell = image::imageops::crop_imm(
+                    &image,
+                    column * self.sprite_width,
+                    row * self.sprite_height,
+                    self.sprite_width,
+                    self.sprite_height,
+                );
+                extrude(
+                    &mut pixels,
+                    &cell.to_image(),
+                    column * cell_width + 1,
+                    row * cell_height + 1,
+                );
+            }
+        }
+        let pixels = match self.image_kind {
+            ImgKind::PNG => DynamicImage::ImageRgba8(pixels),
+            ImgKind::JPEG | ImgKind::JPG => {
+                DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(pixels).to_rgb8())
+            }
+        };
+        let sheet = Texture::new(pixels, self.image_kind, self.storage);
         let mut textures = Vec::new();
         for row in 0..self.rows {
             for column in 0..self.columns {
-                let subimg = image::imageops::crop(
-                    &mut image,
-                    column as u32 * self.sprite_width,
-                    row as u32 * self.sprite_height,
+                let tex = sheet.sub_texture(
+                    column as u32 * cell_width + 1,
+                    row as u32 * cell_height + 1,
                     self.sprite_width,
                     self.sprite_height,
-                )
-                .to_image();
-                let tex = Texture::new(
-                    image::DynamicImage::ImageRgba8(subimg),
-                    self.image_kind.clone(),
                 );
 
                 textures.insert(get_index(column, row, self.columns), tex);
             }
         }
-        println!("Loaded {} textures.", textures.len());
-        textures
+        log::debug!("Loaded {} sprites from {}.", textures.len(), self.img_path);
+        Ok(SpriteSheet {
+            texture: sheet,
+            sprites: textures,
+            columns: self.columns,
+        })
+    }
+
+    /// Identifies the sheet by image and grid, for caching.
+    pub(crate) fn key(&self) -> String {
+        format!(
+            "{}#{}x{}@{}x{}",
+            self.img_path, self.columns, self.rows, self.sprite_width, self.sprite_height
+        )
+    }
+}
+
+/// The sprites cut out of one sheet texture, indexed row by row. Clones
+/// share the sheet texture.
+#[derive(Clone, Debug)]
+pub struct SpriteSheet {
+    pub texture: Texture,
+    sprites: Vec<Texture>,
+    columns: usize,
+}
+impl SpriteSheet {
+    pub fn get(&self, index: usize) -> Option<&Texture> {
+        self.sprites.get(index)
+    }
+
+    /// Sprite in `column` of `row`.
+    pub fn sprite(&self, column: usize, row: usize) -> Option<&Texture> {
+        self.get(get_index(column, row, self.columns))
+    }
+
+    pub fn len(&self) -> usize {
+        self.sprites.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sprites.is_empty()
+    }
+}
+
+impl Index<usize> for SpriteSheet {
+    type Output = Texture;
+
+    fn index(&self, index: usize) -> &Texture {
+        &self.sprites[index]
     }
 }
 
-fn load_image_from_file(img_path: &str) -> DynamicImage {
-    image::open(&Path::new(img_path)).expect("Failed to load an image")
+pub(crate) fn load_image_from_file(img_path: &str) -> Result<DynamicImage> {
+    image::open(Path::new(img_path)).map_err(|source| Error::Image {
+        path: img_path.to_string(),
+        source,
+    })
+}
+
+/// Copies `cell` to (`x`, `y`) of `target` and repeats its outermost pixels
+/// in the ring around it, which must fit in `target`.
+fn extrude(target: &mut RgbaImage, cell: &RgbaImage, x: u32, y: u32) {
+    let (width, height) = cell.dimensions();
+    if width == 0 || height == 0 {
+        return;
+    }
+    for ty in y - 1..y + height + 1 {
+        for tx in x - 1..x + width + 1 {
+            let texel = cell.get_pixel(
+                tx.clamp(x, x + width - 1) - x,
+                ty.clamp(y, y + height - 1) - y,
+            );
+            target.put_pixel(tx, ty, *texel);
+        }
+    }
 }
 
 pub fn get_index(x: usize, y: usize, max_x: usize) -> usize {
     (y * max_x) + x
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn sheet_cells_are_extruded_apart() {
+        // two 2x2 cells side by side, every texel different
+        let sheet = RgbaImage::from_fn(4, 2, |x, y| Rgba([x as u8 * 60, y as u8 * 60, 0, 255]));
+        let path = std::env::temp_dir().join(format!("omak-sheet-{}.png", std::process::id()));
+        sheet.save(&path).unwrap();
+        let sprites = SpritesBuilder::init(path.to_str().unwrap(), ImgKind::PNG)
+            .with_rows(1, 2)
+            .with_columns(2, 2)
+            .with_storage(TextureStorage::Cpu)
+            .try_build_sheet()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let pixels = sprites.texture.pixels().unwrap();
+        for index in 0..sprites.len() {
+            let sprite = &sprites[index];
+            assert_eq!((sprite.width, sprite.height), (2, 2));
+            let left = (sprite.region.x * pixels.width() as f32) as i32;
+            let top = (sprite.region.y * pixels.height() as f32) as i32;
+            // the cell and the ring around it only hold texels of this cell
+            for y in -1..3 {
+                for x in -1..3 {
+                    let expected = sheet.get_pixel(
+                        index as u32 * 2 + x.clamp(0, 1) as u32,
+                        y.clamp(0, 1) as u32,
+                    );
+                    let actual = pixels.get_pixel((left + x) as u32, (top + y) as u32);
+                    assert_eq!(actual, expected, "sprite {} at ({}, {})", index, x, y);
+                }
+            }
+        }
+    }
+}
@@ -1,33 +1,208 @@
+use crate::error::{Error, Result};
+use crate::renderer::assets::{Handle, WeakHandle};
+use crate::renderer::font::{bmfont, Font, TtfFontBuilder};
 use crate::renderer::shader::Shader;
+use crate::renderer::texture::{
+    SpriteSheet, SpritesBuilder, Texture, TextureBuilder, TextureStorage,
+};
+use crate::renderer::ImgKind;
 use std::collections::HashMap;
 use std::fs;
+use std::time::{Duration, Instant, SystemTime};
 
+/// How often `reload_modified` looks at the shader files.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A live asset and the modification time of the file it was built from.
+struct Watched<T> {
+    asset: WeakHandle<T>,
+    modified: Option<SystemTime>,
+}
+impl<T> Watched<T> {
+    fn new(asset: &Handle<T>, modified: Option<SystemTime>) -> Self {
+        Self {
+            asset: asset.downgrade(),
+            modified,
+        }
+    }
+
+    /// Whether the file at `path` changed since it was last looked at. The
+    /// new time is remembered either way, so a version that fails to build is
+    /// not rebuilt every poll.
+    fn changed(&mut self, path: &str) -> bool {
+        let modified = modified_time(path);
+        if modified.is_none() || modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        true
+    }
+
+    /// Hands the rebuilt asset to `swap` if the live one is still around.
+    /// A failed build keeps the live asset as it is, and is logged.
+    fn reload<N>(&self, path: &str, rebuilt: Result<N>, swap: impl FnOnce(&T, N)) {
+        match (rebuilt, self.asset.upgrade()) {
+            (Ok(rebuilt), Some(live)) => {
+                swap(&live, rebuilt);
+                log::info!("Reloaded {}", path);
+            }
+            (Err(e), _) => log::error!("Keeping the previous version: {}", e),
+            (Ok(_), None) => {}
+        }
+    }
+}
+
+/// Loads textures, sprite sheets, fonts and shaders once per path and hands out
+/// shared `Handle`s to them. Assets are released when their last handle is
+/// dropped, not when the manager is.
 pub struct ResourcesManager {
-    cached_shaders: HashMap<String, Shader>,
+    cached_textures: HashMap<String, WeakHandle<Texture>>,
+    cached_sprite_sheets: HashMap<String, WeakHandle<SpriteSheet>>,
+    cached_fonts: HashMap<String, WeakHandle<Font>>,
+    cached_shaders: HashMap<String, Watched<Shader>>,
+    hot_reload: bool,
+    last_poll: Instant,
+    texture_storage: TextureStorage,
 }
 
 impl ResourcesManager {
     pub fn new() -> Self {
         Self {
+            cached_textures: HashMap::new(),
+            cached_sprite_sheets: HashMap::new(),
+            cached_fonts: HashMap::new(),
             cached_shaders: HashMap::new(),
+            hot_reload: cfg!(debug_assertions),
+            last_poll: Instant::now(),
+            texture_storage: TextureStorage::Gpu,
         }
     }
-    pub fn load_shader(&mut self, shader_path: &str) -> &mut Shader {
-        let name = format!("{shader_path}");
-        if self.cached_shaders.contains_key(&name) {
-            return self.cached_shaders.get_mut(&name).unwrap();
+
+    /// Where textures, sheets and fonts are loaded from now on. The
+    /// `Renderer` sets it to the storage its backend draws from.
+    pub fn set_texture_storage(&mut self, storage: TextureStorage) {
+        self.texture_storage = storage;
+    }
+
+    pub fn texture_storage(&self) -> TextureStorage {
+        self.texture_storage
+    }
+
+    /// Watch shader sources for changes, on by default in debug builds.
+    pub fn set_hot_reload(&mut self, enabled: bool) {
+        self.hot_reload = enabled;
+    }
+
+    /// Recompiles the live shaders whose file changed since they were built.
+    /// A shader that fails to compile or link keeps its previous program, and
+    /// the log is reported as an error. Cheap to call every frame: the files
+    /// are only polled every `POLL_INTERVAL`.
+    pub fn reload_modified(&mut self) {
+        if !self.hot_reload || !poll_due(&mut self.last_poll, Instant::now()) {
+            return;
         }
-        let shader_source = self.parse_shader(shader_path);
-        let new_shader = Shader::new(shader_source);
-        self.cached_shaders.insert(name.clone(), new_shader);
-        self.cached_shaders.get_mut(&name).unwrap()
+
+        self.cached_shaders
+            .retain(|_, watched| watched.asset.upgrade().is_some());
+        let mut changed: Vec<String> = Vec::new();
+        for (path, watched) in &mut self.cached_shaders {
+            if watched.changed(path) {
+                changed.push(path.clone());
+            }
+        }
+        for path in changed {
+            let rebuilt = self
+                .parse_shader(&path)
+                .and_then(|source| Shader::new(&path, source));
+            self.cached_shaders[&path].reload(&path, rebuilt, Shader::replace_program);
+        }
+    }
+
+    pub fn load_texture(&mut self, img_path: &str) -> Handle<Texture> {
+        self.try_load_texture(img_path)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Whole image as a texture, its kind guessed from the extension.
+    pub fn try_load_texture(&mut self, img_path: &str) -> Result<Handle<Texture>> {
+        load_cached(&mut self.cached_textures, img_path, || {
+            TextureBuilder::init(img_path, ImgKind::from_path(img_path))
+                .with_storage(self.texture_storage)
+                .try_build()
+        })
+    }
+
+    pub fn load_sprite_sheet(&mut self, builder: SpritesBuilder) -> Handle<SpriteSheet> {
+        self.try_load_sprite_sheet(builder)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Sheets are cached by image and grid, so the same image cut differently
+    /// is loaded twice.
+    pub fn try_load_sprite_sheet(
+        &mut self,
+        builder: SpritesBuilder,
+    ) -> Result<Handle<SpriteSheet>> {
+        let key = builder.key();
+        let builder = builder.with_storage(self.texture_storage);
+        load_cached(&mut self.cached_sprite_sheets, &key, || {
+            builder.try_build_sheet()
+        })
+    }
+
+    pub fn load_font(&mut self, fnt_path: &str) -> Handle<Font> {
+        self.try_load_font(fnt_path)
+            .unwrap_or_else(|e| panic!("{}", e))
     }
 
-    fn parse_shader(&mut self, shader_path: &str) -> (String, String) {
+    /// BMFont from its text `.fnt`, see `Font::load_bmfont`.
+    pub fn try_load_font(&mut self, fnt_path: &str) -> Result<Handle<Font>> {
+        let storage = self.texture_storage;
+        load_cached(&mut self.cached_fonts, fnt_path, || {
+            bmfont::load(fnt_path, storage)
+        })
+    }
+
+    pub fn load_ttf_font(&mut self, builder: TtfFontBuilder) -> Handle<Font> {
+        self.try_load_ttf_font(builder)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// TrueType fonts are cached by file and size, whatever chars were asked for.
+    pub fn try_load_ttf_font(&mut self, builder: TtfFontBuilder) -> Result<Handle<Font>> {
+        let key = builder.key();
+        let builder = builder.with_storage(self.texture_storage);
+        load_cached(&mut self.cached_fonts, &key, || builder.try_build())
+    }
+
+    pub fn load_shader(&mut self, shader_path: &str) -> Handle<Shader> {
+        self.try_load_shader(shader_path)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_load_shader(&mut self, shader_path: &str) -> Result<Handle<Shader>> {
+        if let Some(shader) = self
+            .cached_shaders
+            .get(shader_path)
+            .and_then(|watched| watched.asset.upgrade())
+        {
+            return Ok(shader);
+        }
+        let modified = modified_time(shader_path);
+        let shader_source = self.parse_shader(shader_path)?;
+        let shader = Handle::new(shader_path, Shader::new(shader_path, shader_source)?);
+        self.cached_shaders
+            .insert(shader_path.to_string(), Watched::new(&shader, modified));
+        Ok(shader)
+    }
+
+    fn parse_shader(&mut self, shader_path: &str) -> Result<(String, String)> {
         let mut kind = -1;
         let (mut vertex, mut fragment) = (String::new(), String::new());
-        let contents =
-            fs::read_to_string(&shader_path).expect("Should have been able to read the file");
+        let contents = fs::read_to_string(shader_path).map_err(|source| Error::Io {
+            path: shader_path.to_string(),
+            source,
+        })?;
         for line in contents.lines() {
             if line.contains("#shader") {
                 if line.contains("vertex") {
@@ -49,6 +224,102 @@ impl ResourcesManager {
                 }
             }
         }
-        (vertex, fragment)
+        Ok((vertex, fragment))
+    }
+}
+
+/// Returns the live asset cached under `key`, or loads and caches it.
+fn load_cached<T>(
+    cache: &mut HashMap<String, WeakHandle<T>>,
+    key: &str,
+    load: impl FnOnce() -> Result<T>,
+) -> Result<Handle<T>> {
+    if let Some(handle) = cache.get(key).and_then(WeakHandle::upgrade) {
+        return Ok(handle);
+    }
+    let handle = Handle::new(key, load()?);
+    cache.insert(key.to_string(), handle.downgrade());
+    Ok(handle)
+}
+
+/// Whether `POLL_INTERVAL` went by since the last poll, which is then `now`.
+fn poll_due(last_poll: &mut Instant, now: Instant) -> bool {
+    if now.duration_since(*last_poll) < POLL_INTERVAL {
+        return false;
+    }
+    *last_poll = now;
+    true
+}
+
+fn modified_time(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::fs::File;
+
+    fn broken(path: &str) -> Error {
+        Error::Shader {
+            path: path.to_string(),
+            stage: "fragment".to_string(),
+            log: "syntax error".to_string(),
+        }
+    }
+
+    #[test]
+    fn polls_are_spaced_by_the_interval() {
+        let start = Instant::now();
+        let mut last_poll = start;
+        assert!(!poll_due(&mut last_poll, start + POLL_INTERVAL / 2));
+        assert!(poll_due(&mut last_poll, start + POLL_INTERVAL));
+        assert_eq!(last_poll, start + POLL_INTERVAL);
+        assert!(!poll_due(&mut last_poll, start + POLL_INTERVAL * 3 / 2));
+        assert!(poll_due(&mut last_poll, start + POLL_INTERVAL * 2));
+    }
+
+    #[test]
+    fn each_change_of_the_file_is_reported_once() {
+        let path = std::env::temp_dir().join(format!("omak-watched-{}.shader", std::process::id()));
+        let path_str = path.to_str().unwrap();
+        fs::write(&path, "#shader vertex").unwrap();
+        let asset = Handle::new(path_str, ());
+        let mut watched = Watched::new(&asset, modified_time(path_str));
+        assert!(!watched.changed(path_str));
+
+        let later = SystemTime::now() + Duration::from_secs(10);
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        assert!(watched.changed(path_str));
+        // remembered even though nothing was rebuilt yet
+        assert!(!watched.changed(path_str));
+
+        // a file being saved may be missing for a moment
+        fs::remove_file(&path).unwrap();
+        assert!(!watched.changed(path_str));
+    }
+
+    #[test]
+    fn failed_rebuilds_keep_the_live_asset() {
+        let asset = Handle::new("a.shader", RefCell::new("first"));
+        let watched = Watched::new(&asset, None);
+        let swap = |live: &RefCell<&str>, rebuilt| *live.borrow_mut() = rebuilt;
+
+        watched.reload("a.shader", Err(broken("a.shader")), swap);
+        assert_eq!(*asset.borrow(), "first");
+        watched.reload("a.shader", Ok("second"), swap);
+        assert_eq!(*asset.borrow(), "second");
+
+        // nothing is left to swap into once the last handle is gone
+        drop(asset);
+        watched.reload("a.shader", Ok("third"), |_, _| {
+            panic!("swapped into a dropped asset")
+        });
     }
 }
@@ -0,0 +1,131 @@
+use nalgebra_glm as glm;
+
+/// How a virtual resolution is scaled up to the window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scaling {
+    /// Largest scale keeping the aspect ratio; bars fill the rest of the window.
+    Fit,
+    /// Largest whole scale, at least 1, so pixel art stays crisp.
+    Integer,
+    /// Fills the window, distorting the picture when its aspect ratio differs.
+    Stretch,
+}
+
+/// A fixed logical size the game draws in, whatever the window size.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VirtualResolution {
+    pub width: u32,
+    pub height: u32,
+    pub scaling: Scaling,
+}
+
+impl VirtualResolution {
+    pub fn new(width: u32, height: u32, scaling: Scaling) -> Self {
+        Self {
+            width,
+            height,
+            scaling,
+        }
+    }
+}
+
+/// Rectangle of the window the frame is drawn into, in window pixels from
+/// the top left corner. What is left of the window are the letterbox bars.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub position: glm::Vec2,
+    pub size: glm::Vec2,
+}
+
+impl Viewport {
+    /// The whole window.
+    pub fn full(window: glm::Vec2) -> Self {
+        Self {
+            position: glm::Vec2::zeros(),
+            size: window,
+        }
+    }
+
+    /// Where a frame of `resolution` lands in a `window` sized window,
+    /// centered and rounded to whole pixels.
+    pub fn letterbox(window: glm::Vec2, resolution: &VirtualResolution) -> Self {
+        let logical = glm::vec2(resolution.width as f32, resolution.height as f32);
+        let fit = window.x / logical.x;
+        let fit = fit.min(window.y / logical.y);
+        let scale = match resolution.scaling {
+            Scaling::Fit => glm::vec2(fit, fit),
+            Scaling::Integer => {
+                let scale = fit.floor().max(1.0);
+                glm::vec2(scale, scale)
+            }
+            Scaling::Stretch => return Self::full(window),
+        };
+        let size = glm::round(&logical.component_mul(&scale));
+        Self {
+            position: glm::floor(&((window - size) * 0.5)),
+            size,
+        }
+    }
+
+    /// Maps a point in window pixels to a `logical` sized frame drawn in the
+    /// viewport. `None` while the viewport is empty, e.g. in a minimized window.
+    pub fn window_to_logical(&self, point: glm::Vec2, logical: glm::Vec2) -> Option<glm::Vec2> {
+        if self.size.x <= 0.0 || self.size.y <= 0.0 {
+            return None;
+        }
+        Some(
+            (point - self.position)
+                .component_mul(&logical)
+                .component_div(&self.size),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn letterbox_centers_the_frame() {
+        let resolution = VirtualResolution::new(320, 180, Scaling::Fit);
+        let viewport = Viewport::letterbox(glm::vec2(800.0, 600.0), &resolution);
+        assert_eq!(viewport.size, glm::vec2(800.0, 450.0));
+        assert_eq!(viewport.position, glm::vec2(0.0, 75.0));
+
+        let resolution = VirtualResolution::new(320, 180, Scaling::Integer);
+        let viewport = Viewport::letterbox(glm::vec2(800.0, 600.0), &resolution);
+        assert_eq!(viewport.size, glm::vec2(640.0, 360.0));
+        assert_eq!(viewport.position, glm::vec2(80.0, 120.0));
+    }
+
+    #[test]
+    fn window_points_map_to_logical_pixels() {
+        let resolution = VirtualResolution::new(320, 180, Scaling::Integer);
+        let viewport = Viewport::letterbox(glm::vec2(800.0, 600.0), &resolution);
+        let logical = glm::vec2(320.0, 180.0);
+        assert_eq!(
+            viewport.window_to_logical(glm::vec2(80.0, 120.0), logical),
+            Some(glm::vec2(0.0, 0.0))
+        );
+        assert_eq!(
+            viewport.window_to_logical(glm::vec2(400.0, 300.0), logical),
+            Some(glm::vec2(160.0, 90.0))
+        );
+    }
+
+    #[test]
+    fn empty_window_maps_nothing() {
+        let resolution = VirtualResolution::new(320, 180, Scaling::Fit);
+        let viewport = Viewport::letterbox(glm::Vec2::zeros(), &resolution);
+        let logical = glm::vec2(320.0, 180.0);
+        assert_eq!(
+            viewport.window_to_logical(glm::Vec2::zeros(), logical),
+            None
+        );
+        let viewport = Viewport::full(glm::Vec2::zeros());
+        assert_eq!(
+            viewport.window_to_logical(glm::Vec2::zeros(), logical),
+            None
+        );
+    }
+}